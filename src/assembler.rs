@@ -0,0 +1,483 @@
+pub mod error;
+pub(crate) mod parser;
+pub(crate) mod utils;
+
+use crate::decoder::constants::{EffectiveAddressCalculation, Instruction, Op};
+use crate::decoder::decoded_instruction::{DecodedInstruction, MemoryAddress, Operand, Width};
+use crate::encoder::encode;
+use crate::encoder::utils::encode_prefixes;
+use error::AssembleError;
+use parser::*;
+use std::collections::{HashMap, HashSet};
+use utils::*;
+
+// Label offsets only move forward as short jumps grow, so this is never
+// reached by real programs. Running out of passes is an error all the same.
+const MAX_PASSES: usize = 64;
+
+struct Line {
+    number: usize,
+    parsed: ParsedLine,
+}
+
+// Everything a line needs to be turned into bytes at a given offset.
+struct Layout<'a> {
+    labels: &'a HashMap<String, usize>,
+    // Plain `jmp`s that didn't reach as short jumps and are now near.
+    near_jumps: &'a mut HashSet<usize>,
+    // While the labels settle, relative displacements are left at zero so an
+    // out-of-range jump doesn't fail a pass that is thrown away.
+    settling: bool,
+}
+
+impl Layout<'_> {
+    fn resolve(&self, value: &Value, offset: usize) -> i32 {
+        match value {
+            Value::Number(number) => *number,
+            Value::Here(number) => offset as i32 + number,
+            // Forward labels are unknown on the first pass.
+            Value::Label(label) => *self.labels.get(label).unwrap_or(&offset) as i32,
+        }
+    }
+}
+
+fn immediate(line: &usize, value: i32) -> Result<i16, AssembleError> {
+    // Both signed and unsigned spellings of a 16-bit value are accepted.
+    match value {
+        -32768..=65535 => Ok(value as u16 as i16),
+        _ => Err(AssembleError::InvalidOperands { line: *line }),
+    }
+}
+
+fn is_accumulator(register: &EffectiveAddressCalculation) -> bool {
+    matches!(
+        register,
+        EffectiveAddressCalculation::AL | EffectiveAddressCalculation::AX
+    )
+}
+
+fn is_direct(address: &MemoryAddress) -> bool {
+    address.base.is_none()
+}
+
+fn build_instruction(
+    statement: &Statement,
+    line: &usize,
+    offset: &usize,
+    index: &usize,
+    layout: &mut Layout,
+) -> Result<DecodedInstruction, AssembleError> {
+    let invalid = AssembleError::InvalidOperands { line: *line };
+
+    let (op, string_width) =
+        which_op(&statement.mnemonic).ok_or(AssembleError::UnknownMnemonic {
+            line: *line,
+            mnemonic: statement.mnemonic.clone(),
+        })?;
+
+    let mut prefixes = statement.prefixes.clone();
+    let arguments = statement.arguments.as_slice();
+
+    // A segment written on the memory operand is a prefix like any other.
+    for argument in arguments {
+        if let Argument::Memory(MemoryAddress {
+            segment: Some(segment_register),
+            ..
+        }) = argument.argument
+        {
+            prefixes.segment = Some(segment_register);
+        }
+    }
+
+    // `byte` or `word` in front of a register has to agree with it.
+    for argument in arguments {
+        if let (Argument::Register(register), Some(width)) = (&argument.argument, argument.width) {
+            if which_register_width(register) != Some(width) {
+                return Err(invalid);
+            }
+        }
+    }
+
+    let width = string_width.or_else(|| arguments.iter().find_map(|argument| argument.width));
+    let distance = arguments.iter().find_map(|argument| argument.distance);
+    // A size written where a register already implies one asks for the
+    // longer register/memory form, which is how the formatter marks it.
+    let sized = arguments.iter().any(|argument| argument.width.is_some());
+
+    let mut operands: Vec<Operand> = Vec::new();
+    for argument in arguments {
+        let operand = match &argument.argument {
+            Argument::Register(register) => Operand::Register(*register),
+            Argument::SegmentRegister(segment_register) => {
+                Operand::SegmentRegister(*segment_register)
+            }
+            Argument::Memory(address) => Operand::Memory(MemoryAddress {
+                segment: prefixes.segment,
+                ..*address
+            }),
+            Argument::Value(value) => {
+                Operand::Immediate(immediate(line, layout.resolve(value, *offset))?)
+            }
+            Argument::Intersegment { segment, offset } => Operand::Intersegment {
+                segment: *segment,
+                offset: *offset,
+            },
+        };
+        operands.push(operand);
+    }
+
+    // Jump, loop and call targets are kept aside, their displacement depends
+    // on the length of the instruction.
+    let target = match (op, arguments) {
+        (
+            Op::Call
+            | Op::Jmp
+            | Op::Je
+            | Op::Jl
+            | Op::Jle
+            | Op::Jb
+            | Op::Jbe
+            | Op::Jp
+            | Op::Jo
+            | Op::Js
+            | Op::Jne
+            | Op::Jnl
+            | Op::Jg
+            | Op::Jnb
+            | Op::Ja
+            | Op::Jnp
+            | Op::Jno
+            | Op::Jns
+            | Op::Loop
+            | Op::Loopz
+            | Op::Loopnz
+            | Op::Jcxz,
+            [ParsedArgument {
+                argument: Argument::Value(value),
+                ..
+            }],
+        ) => Some(layout.resolve(value, *offset)),
+        _ => None,
+    };
+
+    let instruction = match (op, operands.as_slice()) {
+        (Op::Mov, [Operand::SegmentRegister(_), _]) => {
+            Instruction::MovRegisterMemoryToSegmentRegister
+        }
+        (Op::Mov, [_, Operand::SegmentRegister(_)]) => {
+            Instruction::MovSegmentRegisterToRegisterMemory
+        }
+        (Op::Mov, [Operand::Register(register), Operand::Memory(address)])
+            if is_accumulator(register) && is_direct(address) =>
+        {
+            Instruction::MovMemoryToAccumulator
+        }
+        (Op::Mov, [Operand::Memory(address), Operand::Register(register)])
+            if is_accumulator(register) && is_direct(address) =>
+        {
+            Instruction::MovAccumulatorToMemory
+        }
+        (Op::Mov, [Operand::Register(_), Operand::Immediate(_)]) if !sized => {
+            Instruction::MovImmediateToRegister
+        }
+        (Op::Mov, [_, Operand::Immediate(_)]) => Instruction::MovImmediateToRegisterMemory,
+        (Op::Mov, [_, _]) => Instruction::MovRegisterMemoryToFromRegister,
+        (
+            Op::Add | Op::Adc | Op::Sub | Op::Sbb | Op::Cmp | Op::And | Op::Test | Op::Or | Op::Xor,
+            [first, second],
+        ) => {
+            let (register_memory, immediate_to_register_memory, immediate_to_accumulator) =
+                which_arithmetic_forms(&op).ok_or(invalid.clone())?;
+
+            match (first, second) {
+                // NASM keeps the sign-extended form for word values that fit
+                // in a byte, except for TEST which has none.
+                (Operand::Register(register), Operand::Immediate(data))
+                    if is_accumulator(register)
+                        && !sized
+                        && (op == Op::Test
+                            || *register == EffectiveAddressCalculation::AL
+                            || i8::try_from(*data).is_err()) =>
+                {
+                    immediate_to_accumulator
+                }
+                (_, Operand::Immediate(_)) => immediate_to_register_memory,
+                _ => register_memory,
+            }
+        }
+        (Op::Push, [Operand::Register(_)]) if !sized => Instruction::PushRegister,
+        (Op::Push, [Operand::SegmentRegister(_)]) => Instruction::PushSegmentRegister,
+        (Op::Push, [_]) => Instruction::PushRegisterMemory,
+        (Op::Pop, [Operand::Register(_)]) if !sized => Instruction::PopRegister,
+        (Op::Pop, [Operand::SegmentRegister(_)]) => Instruction::PopSegmentRegister,
+        (Op::Pop, [_]) => Instruction::PopRegisterMemory,
+        (Op::Xchg, [Operand::Register(first), Operand::Register(second)])
+            if !sized
+                && (*first == EffectiveAddressCalculation::AX
+                    && which_register_width(second) == Some(Width::Word)
+                    || *second == EffectiveAddressCalculation::AX
+                        && which_register_width(first) == Some(Width::Word)) =>
+        {
+            // The short form always puts AX first.
+            if *first != EffectiveAddressCalculation::AX {
+                operands.swap(0, 1);
+            }
+            Instruction::XchgRegisterWithAccumulator
+        }
+        (Op::Xchg, [_, _]) => Instruction::XchgRegisterMemoryWithRegister,
+        (Op::Xchg, []) if statement.mnemonic == "nop" => {
+            // `nop` is the one-byte `xchg ax, ax`.
+            operands.extend([Operand::Register(EffectiveAddressCalculation::AX); 2]);
+            Instruction::XchgRegisterWithAccumulator
+        }
+        (Op::In, [_, Operand::Immediate(_)]) => Instruction::InFixedPort,
+        (Op::In, [_, _]) => Instruction::InVariablePort,
+        (Op::Out, [Operand::Immediate(_), _]) => Instruction::OutFixedPort,
+        (Op::Out, [_, _]) => Instruction::OutVariablePort,
+        (Op::Inc, [Operand::Register(register)])
+            if !sized && which_register_width(register) == Some(Width::Word) =>
+        {
+            Instruction::IncRegister
+        }
+        (Op::Inc, [_]) => Instruction::IncRegisterMemory,
+        (Op::Dec, [Operand::Register(register)])
+            if !sized && which_register_width(register) == Some(Width::Word) =>
+        {
+            Instruction::DecRegister
+        }
+        (Op::Dec, [_]) => Instruction::DecRegisterMemory,
+        (Op::Call, [_]) if target.is_some() => Instruction::CallDirectWithinSegment,
+        (Op::Call, [Operand::Intersegment { .. }]) => Instruction::CallDirectIntersegment,
+        (Op::Call, [_]) if distance == Some(Distance::Far) => Instruction::CallIndirectIntersegment,
+        (Op::Call, [_]) => Instruction::CallIndirectWithinSegment,
+        (Op::Jmp, [_]) if target.is_some() => match distance {
+            Some(Distance::Short) => Instruction::JmpDirectWithinSegmentShort,
+            Some(Distance::Near) => Instruction::JmpDirectWithinSegment,
+            _ if layout.near_jumps.contains(index) => Instruction::JmpDirectWithinSegment,
+            _ => Instruction::JmpDirectWithinSegmentShort,
+        },
+        (Op::Jmp, [Operand::Intersegment { .. }]) => Instruction::JmpDirectIntersegment,
+        (Op::Jmp, [_]) if distance == Some(Distance::Far) => Instruction::JmpIndirectIntersegment,
+        (Op::Jmp, [_]) => Instruction::JmpIndirectWithinSegment,
+        (Op::Ret, []) => Instruction::RetWithinSegment,
+        (Op::Ret, [_]) => Instruction::RetWithinSegmentAddingImmediateToSp,
+        (Op::Retf, []) => Instruction::RetIntersegment,
+        (Op::Retf, [_]) => Instruction::RetIntersegmentAddingImmediateToSp,
+        (Op::Int, [_]) => Instruction::InterruptTypeSpecified,
+        (Op::Aam | Op::Aad, []) => {
+            // The base is implied when it is the usual 10.
+            operands.push(Operand::Immediate(10));
+            which_single_form(&op).ok_or(invalid.clone())?
+        }
+        _ => which_single_form(&op).ok_or(invalid.clone())?,
+    };
+
+    let mut decoded = DecodedInstruction::new(op, instruction, prefixes, *offset);
+    decoded.width = width;
+    decoded.operands = operands;
+
+    // A register/memory operand given a size that disagrees with it.
+    if let (Some(width), Some(Operand::Register(register))) =
+        (decoded.width, decoded.operands.first())
+    {
+        let checked = !matches!(
+            instruction,
+            Instruction::OutFixedPort | Instruction::OutVariablePort
+        );
+        if checked && which_register_width(register) != Some(width) {
+            return Err(invalid);
+        }
+    }
+
+    if let Some(target) = target {
+        // The displacement counts from the end of the instruction, so encode
+        // it once to learn its length.
+        decoded.operands = vec![Operand::Relative(0)];
+        decoded.length = encode(&decoded)
+            .map_err(|error| AssembleError::Encode { line: *line, error })?
+            .len();
+
+        let displacement = target - (*offset + decoded.length) as i32;
+
+        // A plain `jmp` starts out short and becomes near for good once its
+        // target is out of reach.
+        if instruction == Instruction::JmpDirectWithinSegmentShort
+            && distance.is_none()
+            && i8::try_from(displacement).is_err()
+        {
+            layout.near_jumps.insert(*index);
+            return build_instruction(statement, line, offset, index, layout);
+        }
+
+        if !layout.settling {
+            decoded.operands = vec![Operand::Relative(displacement as u16 as i16)];
+        }
+    }
+
+    Ok(decoded)
+}
+
+fn assemble_line(
+    line: &Line,
+    offset: &usize,
+    index: &usize,
+    layout: &mut Layout,
+) -> Result<Vec<u8>, AssembleError> {
+    let mut bytes: Vec<u8> = Vec::new();
+
+    match &line.parsed {
+        ParsedLine::Label(_) => {}
+        ParsedLine::Data { width, values } => {
+            for value in values {
+                let data = layout.resolve(value, *offset + bytes.len());
+
+                match (width, value) {
+                    // A label that doesn't fit is only a guess while settling.
+                    (Width::Byte, Value::Label(_)) => bytes.push(data as u8),
+                    (Width::Byte, _) if (-128..=255).contains(&data) => bytes.push(data as u8),
+                    (Width::Word, _) => {
+                        bytes.extend(immediate(&line.number, data)?.to_le_bytes());
+                    }
+                    _ => return Err(AssembleError::InvalidOperands { line: line.number }),
+                }
+            }
+        }
+        ParsedLine::Instruction(statement) if statement.mnemonic.is_empty() => {
+            encode_prefixes(&mut bytes, &statement.prefixes, &statement.prefixes.segment);
+        }
+        ParsedLine::Instruction(statement) => {
+            let decoded = build_instruction(statement, &line.number, offset, index, layout)?;
+
+            bytes = encode(&decoded).map_err(|error| AssembleError::Encode {
+                line: line.number,
+                error,
+            })?;
+        }
+    }
+
+    Ok(bytes)
+}
+
+fn check_labels(lines: &[Line]) -> Result<(), AssembleError> {
+    let mut defined: HashSet<&str> = HashSet::new();
+
+    for line in lines {
+        if let ParsedLine::Label(label) = &line.parsed {
+            if !defined.insert(label) {
+                return Err(AssembleError::DuplicateLabel {
+                    line: line.number,
+                    label: label.clone(),
+                });
+            }
+        }
+    }
+
+    for line in lines {
+        let values: Vec<&Value> = match &line.parsed {
+            ParsedLine::Data { values, .. } => values.iter().collect(),
+            ParsedLine::Instruction(statement) => statement
+                .arguments
+                .iter()
+                .filter_map(|argument| match &argument.argument {
+                    Argument::Value(value) => Some(value),
+                    _ => None,
+                })
+                .collect(),
+            ParsedLine::Label(_) => Vec::new(),
+        };
+
+        for value in values {
+            if let Value::Label(label) = value {
+                if !defined.contains(label.as_str()) {
+                    return Err(AssembleError::UndefinedLabel {
+                        line: line.number,
+                        label: label.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Assembles NASM-style 16-bit source, the same syntax the formatter writes,
+/// into 8086 machine code. Lines are laid out again until every label
+/// settles, so plain `jmp`s are short wherever their target is in reach.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AssembleError> {
+    let mut lines: Vec<Line> = Vec::new();
+
+    for (index, text) in source.lines().enumerate() {
+        let parsed = parse_line(text).ok_or(AssembleError::Syntax {
+            line: index + 1,
+            text: text.trim().to_string(),
+        })?;
+
+        lines.extend(parsed.into_iter().map(|parsed| Line {
+            number: index + 1,
+            parsed,
+        }));
+    }
+
+    check_labels(&lines)?;
+
+    let mut labels: HashMap<String, usize> = HashMap::new();
+    let mut near_jumps: HashSet<usize> = HashSet::new();
+    // The line of the first label that moved in the last pass.
+    let mut unsettled: Option<usize> = None;
+
+    for _ in 0..MAX_PASSES {
+        let grown = near_jumps.len();
+        let mut settled_labels: HashMap<String, usize> = HashMap::new();
+        let mut offset: usize = 0;
+
+        let mut layout = Layout {
+            labels: &labels,
+            near_jumps: &mut near_jumps,
+            settling: true,
+        };
+
+        for (index, line) in lines.iter().enumerate() {
+            if let ParsedLine::Label(label) = &line.parsed {
+                settled_labels.insert(label.clone(), offset);
+            }
+
+            offset += assemble_line(line, &offset, &index, &mut layout)?.len();
+        }
+
+        if settled_labels == labels && near_jumps.len() == grown {
+            unsettled = None;
+            break;
+        }
+
+        // A jump that grew moves no label when nothing follows it.
+        unsettled = lines
+            .iter()
+            .find(|line| match &line.parsed {
+                ParsedLine::Label(label) => settled_labels.get(label) != labels.get(label),
+                _ => false,
+            })
+            .or(lines.last())
+            .map(|line| line.number);
+
+        labels = settled_labels;
+    }
+
+    if let Some(line) = unsettled {
+        return Err(AssembleError::Unsettled { line });
+    }
+
+    let mut layout = Layout {
+        labels: &labels,
+        near_jumps: &mut near_jumps,
+        settling: false,
+    };
+    let mut output: Vec<u8> = Vec::new();
+
+    for (index, line) in lines.iter().enumerate() {
+        let offset = output.len();
+        output.extend(assemble_line(line, &offset, &index, &mut layout)?);
+    }
+
+    Ok(output)
+}
@@ -0,0 +1,63 @@
+use crate::encoder::error::EncodeError;
+use std::{error::Error, fmt};
+
+// Assemble Errors, `line` counts from 1
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AssembleError {
+    // `text` can't be read as a label, directive or instruction.
+    Syntax { line: usize, text: String },
+    // `mnemonic` is not an 8086 instruction or directive.
+    UnknownMnemonic { line: usize, mnemonic: String },
+    // The operands match no form of the instruction.
+    InvalidOperands { line: usize },
+    // `label` is used but never defined.
+    UndefinedLabel { line: usize, label: String },
+    // `label` is defined a second time.
+    DuplicateLabel { line: usize, label: String },
+    // The instruction was recognised but its operands don't encode.
+    Encode { line: usize, error: EncodeError },
+    // The label on `line` still moved after the last layout pass.
+    Unsettled { line: usize },
+}
+
+impl AssembleError {
+    pub fn line(&self) -> usize {
+        match self {
+            AssembleError::Syntax { line, .. }
+            | AssembleError::UnknownMnemonic { line, .. }
+            | AssembleError::InvalidOperands { line }
+            | AssembleError::UndefinedLabel { line, .. }
+            | AssembleError::DuplicateLabel { line, .. }
+            | AssembleError::Encode { line, .. }
+            | AssembleError::Unsettled { line } => *line,
+        }
+    }
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssembleError::Syntax { line, text } => {
+                write!(f, "line {}: cannot parse `{}`", line, text)
+            }
+            AssembleError::UnknownMnemonic { line, mnemonic } => {
+                write!(f, "line {}: unknown mnemonic `{}`", line, mnemonic)
+            }
+            AssembleError::InvalidOperands { line } => {
+                write!(f, "line {}: invalid combination of operands", line)
+            }
+            AssembleError::UndefinedLabel { line, label } => {
+                write!(f, "line {}: undefined label `{}`", line, label)
+            }
+            AssembleError::DuplicateLabel { line, label } => {
+                write!(f, "line {}: label `{}` is already defined", line, label)
+            }
+            AssembleError::Encode { line, error } => write!(f, "line {}: {}", line, error),
+            AssembleError::Unsettled { line } => {
+                write!(f, "line {}: label offsets never settle", line)
+            }
+        }
+    }
+}
+
+impl Error for AssembleError {}
@@ -0,0 +1,328 @@
+use crate::assembler::utils::*;
+use crate::decoder::constants::{EffectiveAddressCalculation, Op, SegmentRegister};
+use crate::decoder::decoded_instruction::{MemoryAddress, Prefixes, Width};
+
+// A number, a label, or `$` (the start of the current line) plus a number.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Value {
+    Number(i32),
+    Label(String),
+    Here(i32),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Argument {
+    Register(EffectiveAddressCalculation),
+    SegmentRegister(SegmentRegister),
+    Memory(MemoryAddress),
+    Value(Value),
+    Intersegment { segment: u16, offset: u16 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Distance {
+    Short,
+    Near,
+    Far,
+}
+
+// One operand with the `byte`, `word`, `short`, `near` or `far` written in
+// front of it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ParsedArgument {
+    pub(crate) argument: Argument,
+    pub(crate) width: Option<Width>,
+    pub(crate) distance: Option<Distance>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Statement {
+    pub(crate) prefixes: Prefixes,
+    pub(crate) mnemonic: String,
+    pub(crate) arguments: Vec<ParsedArgument>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ParsedLine {
+    Label(String),
+    Instruction(Statement),
+    Data { width: Width, values: Vec<Value> },
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut characters = text.chars();
+
+    match characters.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' || first == '.' => characters
+            .all(|character| character.is_ascii_alphanumeric() || "_.@$".contains(character)),
+        _ => false,
+    }
+}
+
+// Decimal, `0x` hexadecimal or `h`-suffixed hexadecimal, with an optional
+// leading minus.
+fn parse_number(text: &str) -> Option<i32> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits.trim_start()),
+        None => (false, text),
+    };
+    let lowercase = digits.to_ascii_lowercase();
+
+    let value = if let Some(hex) = lowercase.strip_prefix("0x") {
+        i32::from_str_radix(hex, 16).ok()?
+    } else if let Some(hex) = lowercase.strip_suffix('h') {
+        if !hex.starts_with(|character: char| character.is_ascii_digit()) {
+            return None;
+        }
+        i32::from_str_radix(hex, 16).ok()?
+    } else {
+        lowercase.parse::<i32>().ok()?
+    };
+
+    Some(if negative { -value } else { value })
+}
+
+fn parse_value(text: &str) -> Option<Value> {
+    let text = text.trim();
+
+    if let Some(number) = parse_number(text) {
+        return Some(Value::Number(number));
+    }
+
+    if let Some(rest) = text.strip_prefix('$') {
+        let rest: String = rest.chars().filter(|c| !c.is_whitespace()).collect();
+
+        return match rest.as_str() {
+            "" => Some(Value::Here(0)),
+            _ => {
+                let number = match rest.strip_prefix('+') {
+                    Some(number) => parse_number(number)?,
+                    None => parse_number(&rest)?,
+                };
+                Some(Value::Here(number))
+            }
+        };
+    }
+
+    if is_identifier(text) && which_register(text).is_none() {
+        return Some(Value::Label(text.to_string()));
+    }
+
+    None
+}
+
+// `[bx + si - 4]`, `[bp+di+0x10]`, `[4834]` and the like, without the
+// brackets.
+fn parse_memory(text: &str, segment: Option<SegmentRegister>) -> Option<MemoryAddress> {
+    let mut registers: Vec<String> = Vec::new();
+    let mut displacement: i32 = 0;
+
+    let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut rest = text.as_str();
+    let mut negative = false;
+
+    // Walk the terms, each one preceded by its sign.
+    loop {
+        let end = rest[1.min(rest.len())..]
+            .find(['+', '-'])
+            .map(|index| index + 1)
+            .unwrap_or(rest.len());
+        let term = &rest[..end];
+
+        let (term_negative, term) = match term.as_bytes().first() {
+            Some(b'+') => (false, &term[1..]),
+            Some(b'-') => (true, &term[1..]),
+            _ => (false, term),
+        };
+        let term_negative = term_negative ^ negative;
+        negative = false;
+
+        if term.is_empty() {
+            // `+ -4` leaves an empty term carrying a sign into the next one.
+            negative = term_negative;
+        } else if let Some(number) = parse_number(term) {
+            displacement += if term_negative { -number } else { number };
+        } else if !term_negative {
+            registers.push(term.to_ascii_lowercase());
+        } else {
+            return None;
+        }
+
+        rest = &rest[end..];
+        if rest.is_empty() {
+            break;
+        }
+    }
+
+    let mut registers: Vec<&str> = registers.iter().map(String::as_str).collect();
+    registers.sort_unstable();
+
+    let base = match registers.as_slice() {
+        [] => None,
+        ["bx", "si"] => Some(EffectiveAddressCalculation::BxSi),
+        ["bx", "di"] => Some(EffectiveAddressCalculation::BxDi),
+        ["bp", "si"] => Some(EffectiveAddressCalculation::BpSi),
+        ["bp", "di"] => Some(EffectiveAddressCalculation::BpDi),
+        ["si"] => Some(EffectiveAddressCalculation::Si),
+        ["di"] => Some(EffectiveAddressCalculation::Di),
+        ["bp"] => Some(EffectiveAddressCalculation::Bp),
+        ["bx"] => Some(EffectiveAddressCalculation::Bx),
+        _ => return None,
+    };
+
+    if !(-65535..=65535).contains(&displacement) {
+        return None;
+    }
+
+    Some(MemoryAddress {
+        base,
+        displacement: displacement as u16 as i16,
+        segment,
+    })
+}
+
+fn parse_argument(text: &str) -> Option<ParsedArgument> {
+    let mut rest = text.trim();
+    let mut width = None;
+    let mut distance = None;
+
+    // Size and distance keywords stack in front of the operand.
+    loop {
+        let (word, after) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+
+        match word.to_ascii_lowercase().as_str() {
+            "byte" => width = Some(Width::Byte),
+            "word" => width = Some(Width::Word),
+            "short" => distance = Some(Distance::Short),
+            "near" => distance = Some(Distance::Near),
+            "far" => distance = Some(Distance::Far),
+            _ => break,
+        }
+
+        rest = after.trim_start();
+    }
+
+    let argument = if let Some(register) = which_register(rest) {
+        Argument::Register(register)
+    } else if let Some(segment_register) = which_segment_register_name(rest) {
+        Argument::SegmentRegister(segment_register)
+    } else if let Some(inner) = rest.strip_prefix('[').and_then(|r| r.strip_suffix(']')) {
+        Argument::Memory(parse_memory(inner, None)?)
+    } else if let Some((before, after)) = rest.split_once(':') {
+        let after = after.trim();
+
+        match which_segment_register_name(before.trim()) {
+            Some(segment_register) => {
+                let inner = after.strip_prefix('[')?.strip_suffix(']')?;
+                Argument::Memory(parse_memory(inner, Some(segment_register))?)
+            }
+            None => {
+                let segment = parse_number(before.trim())?;
+                let offset = parse_number(after)?;
+                Argument::Intersegment {
+                    segment: u16::try_from(segment).ok()?,
+                    offset: u16::try_from(offset).ok()?,
+                }
+            }
+        }
+    } else {
+        Argument::Value(parse_value(rest)?)
+    };
+
+    Some(ParsedArgument {
+        argument,
+        width,
+        distance,
+    })
+}
+
+fn split_arguments(text: &str) -> Vec<&str> {
+    if text.trim().is_empty() {
+        Vec::new()
+    } else {
+        text.split(',').map(str::trim).collect()
+    }
+}
+
+/// Reads one line of source into the labels, data and instructions on it.
+/// `None` means the line can't be parsed.
+pub(crate) fn parse_line(text: &str) -> Option<Vec<ParsedLine>> {
+    let text = match text.split_once(';') {
+        Some((code, _comment)) => code,
+        None => text,
+    };
+    let mut rest = text.trim();
+    let mut parsed: Vec<ParsedLine> = Vec::new();
+
+    // A leading `name:` defines a label, an instruction may follow it.
+    if let Some((label, after)) = rest.split_once(':') {
+        let label = label.trim();
+        if is_identifier(label) && which_segment_register_name(label).is_none() {
+            parsed.push(ParsedLine::Label(label.to_string()));
+            rest = after.trim();
+        }
+    }
+
+    if rest.is_empty() {
+        return Some(parsed);
+    }
+
+    let mut prefixes = Prefixes::default();
+
+    loop {
+        let (word, after) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let word = word.to_ascii_lowercase();
+
+        match word.as_str() {
+            "bits" => {
+                return (after.trim() == "16").then_some(parsed);
+            }
+            "db" | "dw" => {
+                let width = if word == "db" {
+                    Width::Byte
+                } else {
+                    Width::Word
+                };
+                let values = split_arguments(after)
+                    .into_iter()
+                    .map(parse_value)
+                    .collect::<Option<Vec<Value>>>()?;
+
+                parsed.push(ParsedLine::Data { width, values });
+                return Some(parsed);
+            }
+            "lock" => prefixes.lock = true,
+            "rep" => prefixes.repeat = Some(Op::Rep),
+            "repe" | "repz" => prefixes.repeat = Some(Op::Repe),
+            "repne" | "repnz" => prefixes.repeat = Some(Op::Repne),
+            _ => match which_segment_register_name(&word) {
+                Some(segment_register) => prefixes.segment = Some(segment_register),
+                None => {
+                    let arguments = split_arguments(after)
+                        .into_iter()
+                        .map(parse_argument)
+                        .collect::<Option<Vec<ParsedArgument>>>()?;
+
+                    parsed.push(ParsedLine::Instruction(Statement {
+                        prefixes,
+                        mnemonic: word,
+                        arguments,
+                    }));
+                    return Some(parsed);
+                }
+            },
+        }
+
+        rest = after.trim_start();
+
+        // A prefix with nothing after it stands on its own line.
+        if rest.is_empty() {
+            parsed.push(ParsedLine::Instruction(Statement {
+                prefixes,
+                mnemonic: String::new(),
+                arguments: Vec::new(),
+            }));
+            return Some(parsed);
+        }
+    }
+}
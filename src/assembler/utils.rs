@@ -0,0 +1,297 @@
+use crate::decoder::constants::*;
+use crate::decoder::decoded_instruction::Width;
+
+pub(crate) fn which_register(name: &str) -> Option<EffectiveAddressCalculation> {
+    let register = match name.to_ascii_lowercase().as_str() {
+        "al" => EffectiveAddressCalculation::AL,
+        "cl" => EffectiveAddressCalculation::CL,
+        "dl" => EffectiveAddressCalculation::DL,
+        "bl" => EffectiveAddressCalculation::BL,
+        "ah" => EffectiveAddressCalculation::AH,
+        "ch" => EffectiveAddressCalculation::CH,
+        "dh" => EffectiveAddressCalculation::DH,
+        "bh" => EffectiveAddressCalculation::BH,
+        "ax" => EffectiveAddressCalculation::AX,
+        "cx" => EffectiveAddressCalculation::CX,
+        "dx" => EffectiveAddressCalculation::DX,
+        "bx" => EffectiveAddressCalculation::BX,
+        "sp" => EffectiveAddressCalculation::SP,
+        "bp" => EffectiveAddressCalculation::BP,
+        "si" => EffectiveAddressCalculation::SI,
+        "di" => EffectiveAddressCalculation::DI,
+        _ => return None,
+    };
+
+    Some(register)
+}
+
+pub(crate) fn which_segment_register_name(name: &str) -> Option<SegmentRegister> {
+    let segment_register = match name.to_ascii_lowercase().as_str() {
+        "es" => SegmentRegister::ES,
+        "cs" => SegmentRegister::CS,
+        "ss" => SegmentRegister::SS,
+        "ds" => SegmentRegister::DS,
+        _ => return None,
+    };
+
+    Some(segment_register)
+}
+
+pub(crate) fn which_register_width(register: &EffectiveAddressCalculation) -> Option<Width> {
+    match register {
+        EffectiveAddressCalculation::AL
+        | EffectiveAddressCalculation::CL
+        | EffectiveAddressCalculation::DL
+        | EffectiveAddressCalculation::BL
+        | EffectiveAddressCalculation::AH
+        | EffectiveAddressCalculation::CH
+        | EffectiveAddressCalculation::DH
+        | EffectiveAddressCalculation::BH => Some(Width::Byte),
+        EffectiveAddressCalculation::AX
+        | EffectiveAddressCalculation::CX
+        | EffectiveAddressCalculation::DX
+        | EffectiveAddressCalculation::BX
+        | EffectiveAddressCalculation::SP
+        | EffectiveAddressCalculation::BP
+        | EffectiveAddressCalculation::SI
+        | EffectiveAddressCalculation::DI => Some(Width::Word),
+        _ => None,
+    }
+}
+
+// The operation a mnemonic names, with the width the string instructions
+// carry in their last letter. NASM's aliases map onto the decoder's names.
+pub(crate) fn which_op(mnemonic: &str) -> Option<(Op, Option<Width>)> {
+    let op = match mnemonic {
+        "mov" => Op::Mov,
+        "add" => Op::Add,
+        "sub" => Op::Sub,
+        "cmp" => Op::Cmp,
+        "push" => Op::Push,
+        "pop" => Op::Pop,
+        "xchg" | "nop" => Op::Xchg,
+        "in" => Op::In,
+        "out" => Op::Out,
+        "xlat" | "xlatb" => Op::Xlat,
+        "lea" => Op::Lea,
+        "lds" => Op::Lds,
+        "les" => Op::Les,
+        "lahf" => Op::Lahf,
+        "sahf" => Op::Sahf,
+        "pushf" => Op::Pushf,
+        "popf" => Op::Popf,
+        "adc" => Op::Adc,
+        "sbb" => Op::Sbb,
+        "inc" => Op::Inc,
+        "dec" => Op::Dec,
+        "neg" => Op::Neg,
+        "mul" => Op::Mul,
+        "imul" => Op::Imul,
+        "div" => Op::Div,
+        "idiv" => Op::Idiv,
+        "aaa" => Op::Aaa,
+        "daa" => Op::Daa,
+        "aas" => Op::Aas,
+        "das" => Op::Das,
+        "aam" => Op::Aam,
+        "aad" => Op::Aad,
+        "cbw" => Op::Cbw,
+        "cwd" => Op::Cwd,
+        "not" => Op::Not,
+        "shl" | "sal" => Op::Shl,
+        "shr" => Op::Shr,
+        "sar" => Op::Sar,
+        "rol" => Op::Rol,
+        "ror" => Op::Ror,
+        "rcl" => Op::Rcl,
+        "rcr" => Op::Rcr,
+        "and" => Op::And,
+        "test" => Op::Test,
+        "or" => Op::Or,
+        "xor" => Op::Xor,
+        "movsb" => return Some((Op::Movs, Some(Width::Byte))),
+        "movsw" => return Some((Op::Movs, Some(Width::Word))),
+        "cmpsb" => return Some((Op::Cmps, Some(Width::Byte))),
+        "cmpsw" => return Some((Op::Cmps, Some(Width::Word))),
+        "scasb" => return Some((Op::Scas, Some(Width::Byte))),
+        "scasw" => return Some((Op::Scas, Some(Width::Word))),
+        "lodsb" => return Some((Op::Lods, Some(Width::Byte))),
+        "lodsw" => return Some((Op::Lods, Some(Width::Word))),
+        "stosb" => return Some((Op::Stos, Some(Width::Byte))),
+        "stosw" => return Some((Op::Stos, Some(Width::Word))),
+        "call" => Op::Call,
+        "jmp" => Op::Jmp,
+        "ret" => Op::Ret,
+        "retf" => Op::Retf,
+        "int" => Op::Int,
+        "int3" => Op::Int3,
+        "into" => Op::Into,
+        "iret" => Op::Iret,
+        "clc" => Op::Clc,
+        "cmc" => Op::Cmc,
+        "stc" => Op::Stc,
+        "cld" => Op::Cld,
+        "std" => Op::Std,
+        "cli" => Op::Cli,
+        "sti" => Op::Sti,
+        "hlt" => Op::Hlt,
+        "wait" | "fwait" => Op::Wait,
+        "esc" => Op::Esc,
+        "je" | "jz" => Op::Je,
+        "jl" | "jnge" => Op::Jl,
+        "jle" | "jng" => Op::Jle,
+        "jb" | "jnae" | "jc" => Op::Jb,
+        "jbe" | "jna" => Op::Jbe,
+        "jp" | "jpe" => Op::Jp,
+        "jo" => Op::Jo,
+        "js" => Op::Js,
+        "jne" | "jnz" => Op::Jne,
+        "jnl" | "jge" => Op::Jnl,
+        "jg" | "jnle" => Op::Jg,
+        "jnb" | "jae" | "jnc" => Op::Jnb,
+        "ja" | "jnbe" => Op::Ja,
+        "jnp" | "jpo" => Op::Jnp,
+        "jno" => Op::Jno,
+        "jns" => Op::Jns,
+        "loop" => Op::Loop,
+        "loopz" | "loope" => Op::Loopz,
+        "loopnz" | "loopne" => Op::Loopnz,
+        "jcxz" => Op::Jcxz,
+        _ => return None,
+    };
+
+    Some((op, None))
+}
+
+// The register/memory with register, immediate to register/memory and
+// immediate to accumulator forms of the two-operand arithmetic and logic
+// instructions.
+pub(crate) fn which_arithmetic_forms(op: &Op) -> Option<(Instruction, Instruction, Instruction)> {
+    let forms = match op {
+        Op::Add => (
+            Instruction::AddRegisterMemoryWithRegisterToEither,
+            Instruction::AddImmediateToRegisterMemory,
+            Instruction::AddImmediateToAccumulator,
+        ),
+        Op::Adc => (
+            Instruction::AdcRegisterMemoryWithRegisterToEither,
+            Instruction::AdcImmediateToRegisterMemory,
+            Instruction::AdcImmediateToAccumulator,
+        ),
+        Op::Sub => (
+            Instruction::SubRegisterMemoryAndRegisterToEither,
+            Instruction::SubImmediateFromRegisterMemory,
+            Instruction::SubImmediateFromAccumulator,
+        ),
+        Op::Sbb => (
+            Instruction::SbbRegisterMemoryAndRegisterToEither,
+            Instruction::SbbImmediateFromRegisterMemory,
+            Instruction::SbbImmediateFromAccumulator,
+        ),
+        Op::Cmp => (
+            Instruction::CmpRegisterMemoryAndRegister,
+            Instruction::CmpImmediateWithRegisterMemory,
+            Instruction::CmpImmediateWithAccumulator,
+        ),
+        Op::And => (
+            Instruction::AndRegisterMemoryWithRegisterToEither,
+            Instruction::AndImmediateToRegisterMemory,
+            Instruction::AndImmediateToAccumulator,
+        ),
+        Op::Test => (
+            Instruction::TestRegisterMemoryAndRegister,
+            Instruction::TestImmediateDataAndRegisterMemory,
+            Instruction::TestImmediateDataAndAccumulator,
+        ),
+        Op::Or => (
+            Instruction::OrRegisterMemoryAndRegisterToEither,
+            Instruction::OrImmediateToRegisterMemory,
+            Instruction::OrImmediateToAccumulator,
+        ),
+        Op::Xor => (
+            Instruction::XorRegisterMemoryAndRegisterToEither,
+            Instruction::XorImmediateToRegisterMemory,
+            Instruction::XorImmediateToAccumulator,
+        ),
+        _ => return None,
+    };
+
+    Some(forms)
+}
+
+// The form of instructions that take a single register/memory operand, or
+// none at all, and are fully named by their operation.
+pub(crate) fn which_single_form(op: &Op) -> Option<Instruction> {
+    let instruction = match op {
+        Op::Xlat => Instruction::TranslateByteToAl,
+        Op::Lea => Instruction::LoadEffectiveAddressToRegister,
+        Op::Lds => Instruction::LoadPointerToDs,
+        Op::Les => Instruction::LoadPointerToEs,
+        Op::Lahf => Instruction::LoadAhWithFlags,
+        Op::Sahf => Instruction::StoreAhIntoFlags,
+        Op::Pushf => Instruction::PushFlags,
+        Op::Popf => Instruction::PopFlags,
+        Op::Neg => Instruction::NegChangeSign,
+        Op::Mul => Instruction::MultiplyUnsigned,
+        Op::Imul => Instruction::IntegerMultiplySigned,
+        Op::Div => Instruction::DivideUnsigned,
+        Op::Idiv => Instruction::IntegerDivideSigned,
+        Op::Aaa => Instruction::AsciiAdjustForAdd,
+        Op::Daa => Instruction::DecimalAdjustForAdd,
+        Op::Aas => Instruction::AsciiAdjustForSubtract,
+        Op::Das => Instruction::DecimalAdjustForSubtract,
+        Op::Aam => Instruction::AsciiAdjustForMultiply,
+        Op::Aad => Instruction::AsciiAdjustForDivide,
+        Op::Cbw => Instruction::ConvertByteToWord,
+        Op::Cwd => Instruction::ConvertWordToDoubleWord,
+        Op::Not => Instruction::NotInvert,
+        Op::Shl => Instruction::ShiftLogicalLeft,
+        Op::Shr => Instruction::ShiftLogicalRight,
+        Op::Sar => Instruction::ShiftArithmeticRight,
+        Op::Rol => Instruction::RotateLeft,
+        Op::Ror => Instruction::RotateRight,
+        Op::Rcl => Instruction::RotateThroughCarryLeft,
+        Op::Rcr => Instruction::RotateThroughCarryRight,
+        Op::Movs => Instruction::MoveByteOrWord,
+        Op::Cmps => Instruction::CompareByteOrWord,
+        Op::Scas => Instruction::ScanByteOrWord,
+        Op::Lods => Instruction::LoadByteOrWordToAlAx,
+        Op::Stos => Instruction::StoreByteOrWordFromAlAx,
+        Op::Int3 => Instruction::InterruptType3,
+        Op::Into => Instruction::InterruptOnOverflow,
+        Op::Iret => Instruction::InterruptReturn,
+        Op::Clc => Instruction::ClearCarry,
+        Op::Cmc => Instruction::ComplementCarry,
+        Op::Stc => Instruction::SetCarry,
+        Op::Cld => Instruction::ClearDirection,
+        Op::Std => Instruction::SetDirection,
+        Op::Cli => Instruction::ClearInterrupt,
+        Op::Sti => Instruction::SetInterrupt,
+        Op::Hlt => Instruction::Halt,
+        Op::Wait => Instruction::WaitForTest,
+        Op::Esc => Instruction::EscapeToExternalDevice,
+        Op::Je => Instruction::JumpOnEqual,
+        Op::Jl => Instruction::JumpOnLess,
+        Op::Jle => Instruction::JumpOnLessOrEqual,
+        Op::Jb => Instruction::JumpOnBelow,
+        Op::Jbe => Instruction::JumpOnBelowOrEqual,
+        Op::Jp => Instruction::JumpOnParity,
+        Op::Jo => Instruction::JumpOnOverflow,
+        Op::Js => Instruction::JumpOnSign,
+        Op::Jne => Instruction::JumpOnNotEqual,
+        Op::Jnl => Instruction::JumpOnNotLess,
+        Op::Jg => Instruction::JumpOnGreater,
+        Op::Jnb => Instruction::JumpOnNotBelow,
+        Op::Ja => Instruction::JumpOnAbove,
+        Op::Jnp => Instruction::JumpOnNotPar,
+        Op::Jno => Instruction::JumpOnNotOverflow,
+        Op::Jns => Instruction::JumpOnNotSign,
+        Op::Loop => Instruction::LoopCxTimes,
+        Op::Loopz => Instruction::LoopWhileZero,
+        Op::Loopnz => Instruction::LoopWhileNotZero,
+        Op::Jcxz => Instruction::JumpOnCxZero,
+        _ => return None,
+    };
+
+    Some(instruction)
+}
@@ -0,0 +1,362 @@
+use crate::decoder::constants::{EffectiveAddressCalculation, Instruction, Op};
+use crate::decoder::decoded_instruction::{DecodedInstruction, MemoryAddress, Operand, Width};
+use crate::decoder::utils::is_prefix;
+use std::fmt;
+
+/// The processor clocks are estimated for. The 8088 is an 8086 with an
+/// 8-bit bus, so every word it moves to or from memory takes two transfers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cpu {
+    I8086,
+    I8088,
+}
+
+impl fmt::Display for Cpu {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = match self {
+            Cpu::I8086 => "8086",
+            Cpu::I8088 => "8088",
+        };
+
+        write!(f, "{}", value)
+    }
+}
+
+/// Looks up a processor by the name `--clocks=` takes.
+pub fn which_cpu(name: &str) -> Option<Cpu> {
+    match name {
+        "8086" => Some(Cpu::I8086),
+        "8088" => Some(Cpu::I8088),
+        _ => None,
+    }
+}
+
+/// The clocks one instruction takes: its base count, the effective address
+/// calculation for a memory operand, and the penalty for word transfers the
+/// bus has to split in two.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Clocks {
+    pub base: u32,
+    pub effective_address: u32,
+    pub penalty: u32,
+}
+
+impl Clocks {
+    pub fn total(&self) -> u32 {
+        self.base + self.effective_address + self.penalty
+    }
+}
+
+// `13 + 8ea + 4p`, leaving out the parts that are zero.
+impl fmt::Display for Clocks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.base)?;
+
+        if self.effective_address != 0 {
+            write!(f, " + {}ea", self.effective_address)?;
+        }
+        if self.penalty != 0 {
+            write!(f, " + {}p", self.penalty)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// What running an instruction tells about its timing that decoding alone
+/// doesn't.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Execution {
+    /// The offset the memory operand refers to. Without it a direct address
+    /// is its own offset and every other address is taken to be even.
+    pub address: Option<u16>,
+    /// Whether a conditional jump, loop or `into` was taken.
+    pub taken: bool,
+}
+
+// Whether the memory operand of the instruction in `bytes` was encoded with
+// a displacement, from the mod field past the prefixes and the opcode. The
+// value can't tell, `[bx + 0]` may still carry a zero byte.
+fn is_displaced(bytes: &[u8]) -> bool {
+    let mod_rm = bytes.iter().skip_while(|byte| is_prefix(byte)).nth(1);
+
+    matches!(mod_rm.map(|mod_rm| mod_rm >> 6), Some(0b01 | 0b10))
+}
+
+// The clocks of an effective address calculation, from the 8086 manual.
+// `[bp]` has no encoding without a displacement, so it always counts one.
+fn which_effective_address(address: &MemoryAddress, displaced: bool) -> u32 {
+    let clocks = match address.base {
+        None => 6,
+        Some(EffectiveAddressCalculation::Bp) => 9,
+        Some(EffectiveAddressCalculation::BpDi | EffectiveAddressCalculation::BxSi) => {
+            if displaced {
+                11
+            } else {
+                7
+            }
+        }
+        Some(EffectiveAddressCalculation::BpSi | EffectiveAddressCalculation::BxDi) => {
+            if displaced {
+                12
+            } else {
+                8
+            }
+        }
+        Some(_) => {
+            if displaced {
+                9
+            } else {
+                5
+            }
+        }
+    };
+
+    // A segment override costs two more.
+    clocks + if address.segment.is_some() { 2 } else { 0 }
+}
+
+// The base clocks of an instruction and how many times it goes to memory,
+// or `None` for the forms whose timing depends on more than the operands,
+// like shifts by CL, multiplies and string instructions.
+fn which_timing(decoded: &DecodedInstruction, taken: bool) -> Option<(u32, u32)> {
+    use Operand::{Immediate as Imm, Memory as Mem, Register as Reg, SegmentRegister as Seg};
+
+    let operands = decoded.operands.as_slice();
+
+    let timing = match decoded.op {
+        Op::Mov => match (decoded.instruction, operands) {
+            (Instruction::MovMemoryToAccumulator | Instruction::MovAccumulatorToMemory, _) => {
+                (10, 1)
+            }
+            (_, [Reg(_) | Seg(_), Reg(_) | Seg(_)]) => (2, 0),
+            (_, [Reg(_) | Seg(_), Mem(_)]) => (8, 1),
+            (_, [Mem(_), Reg(_) | Seg(_)]) => (9, 1),
+            (_, [Reg(_), Imm(_)]) => (4, 0),
+            (_, [Mem(_), Imm(_)]) => (10, 1),
+            _ => return None,
+        },
+        Op::Add | Op::Adc | Op::Sub | Op::Sbb | Op::And | Op::Or | Op::Xor => match operands {
+            [Reg(_), Reg(_)] => (3, 0),
+            [Reg(_), Mem(_)] => (9, 1),
+            [Mem(_), Reg(_)] => (16, 2),
+            [Reg(_), Imm(_)] => (4, 0),
+            [Mem(_), Imm(_)] => (17, 2),
+            _ => return None,
+        },
+        Op::Cmp => match operands {
+            [Reg(_), Reg(_)] => (3, 0),
+            [Reg(_), Mem(_)] | [Mem(_), Reg(_)] => (9, 1),
+            [Reg(_), Imm(_)] => (4, 0),
+            [Mem(_), Imm(_)] => (10, 1),
+            _ => return None,
+        },
+        Op::Test => match (decoded.instruction, operands) {
+            (Instruction::TestImmediateDataAndAccumulator, _) => (4, 0),
+            (_, [Reg(_), Reg(_)]) => (3, 0),
+            (_, [Reg(_), Mem(_)] | [Mem(_), Reg(_)]) => (9, 1),
+            (_, [Reg(_), Imm(_)]) => (5, 0),
+            (_, [Mem(_), Imm(_)]) => (11, 1),
+            _ => return None,
+        },
+        Op::Inc | Op::Dec => match operands {
+            [Reg(_)] if decoded.width == Some(Width::Byte) => (3, 0),
+            [Reg(_)] => (2, 0),
+            [Mem(_)] => (15, 2),
+            _ => return None,
+        },
+        Op::Neg | Op::Not => match operands {
+            [Reg(_)] => (3, 0),
+            [Mem(_)] => (16, 2),
+            _ => return None,
+        },
+        Op::Shl | Op::Shr | Op::Sar | Op::Rol | Op::Ror | Op::Rcl | Op::Rcr => match operands {
+            [Reg(_), Imm(_)] => (2, 0),
+            [Mem(_), Imm(_)] => (15, 2),
+            _ => return None,
+        },
+        Op::Push => match operands {
+            [Reg(_)] => (11, 1),
+            [Seg(_)] => (10, 1),
+            [Mem(_)] => (16, 2),
+            _ => return None,
+        },
+        Op::Pop => match operands {
+            [Reg(_) | Seg(_)] => (8, 1),
+            [Mem(_)] => (17, 2),
+            _ => return None,
+        },
+        Op::Xchg => match (decoded.instruction, operands) {
+            (Instruction::XchgRegisterWithAccumulator, _) => (3, 0),
+            (_, [Reg(_), Reg(_)]) => (4, 0),
+            (_, [Reg(_), Mem(_)] | [Mem(_), Reg(_)]) => (17, 2),
+            _ => return None,
+        },
+        Op::In => match operands {
+            [_, Imm(_)] => (10, 1),
+            _ => (8, 1),
+        },
+        Op::Out => match operands {
+            [Imm(_), _] => (10, 1),
+            _ => (8, 1),
+        },
+        Op::Xlat => (11, 1),
+        Op::Lea => (2, 0),
+        Op::Lds | Op::Les => (16, 2),
+        Op::Lahf | Op::Sahf => (4, 0),
+        Op::Pushf => (10, 1),
+        Op::Popf => (8, 1),
+        Op::Aaa | Op::Aas | Op::Daa | Op::Das => (4, 0),
+        Op::Aam => (83, 0),
+        Op::Aad => (60, 0),
+        Op::Cbw => (2, 0),
+        Op::Cwd => (5, 0),
+        Op::Call => match (decoded.instruction, operands) {
+            (_, [Operand::Relative(_)]) => (19, 1),
+            (_, [Operand::Intersegment { .. }]) => (28, 2),
+            (_, [Reg(_)]) => (16, 1),
+            (Instruction::CallIndirectIntersegment, [Mem(_)]) => (37, 4),
+            (_, [Mem(_)]) => (21, 2),
+            _ => return None,
+        },
+        Op::Jmp => match (decoded.instruction, operands) {
+            (_, [Operand::Relative(_) | Operand::Intersegment { .. }]) => (15, 0),
+            (_, [Reg(_)]) => (11, 0),
+            (Instruction::JmpIndirectIntersegment, [Mem(_)]) => (24, 2),
+            (_, [Mem(_)]) => (18, 1),
+            _ => return None,
+        },
+        Op::Ret => match operands {
+            [] => (8, 1),
+            _ => (12, 1),
+        },
+        Op::Retf => match operands {
+            [] => (18, 2),
+            _ => (17, 2),
+        },
+        Op::Int => (51, 5),
+        Op::Int3 => (52, 5),
+        Op::Into if taken => (53, 5),
+        Op::Into => (4, 0),
+        Op::Iret => (24, 3),
+        Op::Clc | Op::Cmc | Op::Stc | Op::Cld | Op::Std | Op::Cli | Op::Sti | Op::Hlt => (2, 0),
+        Op::Wait => (3, 0),
+        Op::Je
+        | Op::Jl
+        | Op::Jle
+        | Op::Jb
+        | Op::Jbe
+        | Op::Jp
+        | Op::Jo
+        | Op::Js
+        | Op::Jne
+        | Op::Jnl
+        | Op::Jg
+        | Op::Jnb
+        | Op::Ja
+        | Op::Jnp
+        | Op::Jno
+        | Op::Jns => {
+            if taken {
+                (16, 0)
+            } else {
+                (4, 0)
+            }
+        }
+        Op::Loop => {
+            if taken {
+                (17, 0)
+            } else {
+                (5, 0)
+            }
+        }
+        Op::Loopz | Op::Jcxz => {
+            if taken {
+                (18, 0)
+            } else {
+                (6, 0)
+            }
+        }
+        Op::Loopnz => {
+            if taken {
+                (19, 0)
+            } else {
+                (5, 0)
+            }
+        }
+        _ => return None,
+    };
+
+    Some(timing)
+}
+
+// How many of the transfers go to the stack rather than to the memory
+// operand.
+fn which_stack_transfers(decoded: &DecodedInstruction) -> u32 {
+    match (decoded.op, decoded.instruction) {
+        (Op::Push | Op::Pop, _) => 1,
+        (Op::Call, Instruction::CallIndirectIntersegment) => 2,
+        (Op::Call, _) => 1,
+        _ => 0,
+    }
+}
+
+/// Estimates the clocks `decoded` takes on `cpu`, or `None` where the
+/// timing depends on more than its operands. `bytes` are the ones it was
+/// decoded from. Every word transfer costs four clocks more on the 8088, and
+/// on the 8086 every transfer to a memory operand at an odd address. Stack
+/// transfers are taken to be even.
+pub fn estimate_clocks(
+    decoded: &DecodedInstruction,
+    bytes: &[u8],
+    cpu: &Cpu,
+    execution: &Execution,
+) -> Option<Clocks> {
+    let (base, transfers) = which_timing(decoded, execution.taken)?;
+
+    let address = decoded.operands.iter().find_map(|operand| match operand {
+        Operand::Memory(address) => Some(address),
+        _ => None,
+    });
+
+    // The accumulator forms carry the address itself, there's nothing to
+    // calculate.
+    let effective_address = match decoded.instruction {
+        Instruction::MovMemoryToAccumulator | Instruction::MovAccumulatorToMemory => 0,
+        _ => address
+            .map(|address| which_effective_address(address, is_displaced(bytes)))
+            .unwrap_or_default(),
+    };
+
+    let odd = match (execution.address, address) {
+        (Some(offset), Some(_)) => !offset.is_multiple_of(2),
+        (None, Some(address)) if address.base.is_none() => address.displacement % 2 != 0,
+        _ => false,
+    };
+
+    let penalty = match (decoded.width, cpu) {
+        (Some(Width::Byte), _) => 0,
+        (_, Cpu::I8088) => 4 * transfers,
+        _ if odd => 4 * (transfers - which_stack_transfers(decoded)),
+        _ => 0,
+    };
+
+    Some(Clocks {
+        base,
+        effective_address,
+        penalty,
+    })
+}
+
+/// `Clocks: +13 = 17 (8 + 5ea)`: the clocks of one instruction, the running
+/// `total` including them, and how they add up when there's more than a base
+/// count. Instructions without an estimate show `+?`.
+pub fn format_clocks(clocks: Option<&Clocks>, total: u32) -> String {
+    match clocks {
+        Some(clocks) if clocks.effective_address != 0 || clocks.penalty != 0 => {
+            format!("Clocks: +{} = {} ({})", clocks.total(), total, clocks)
+        }
+        Some(clocks) => format!("Clocks: +{} = {}", clocks.total(), total),
+        None => format!("Clocks: +? = {}", total),
+    }
+}
@@ -1,3 +1,5 @@
-pub(crate) mod constants;
+pub mod constants;
+pub mod decoded_instruction;
+pub mod error;
 pub(crate) mod functions;
 pub(crate) mod utils;
@@ -1,11 +1,82 @@
 use std::fmt;
 
 // Operations
-pub(crate) enum Op {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
     Mov,
     Add,
     Sub,
     Cmp,
+    Push,
+    Pop,
+    Xchg,
+    In,
+    Out,
+    Xlat,
+    Lea,
+    Lds,
+    Les,
+    Lahf,
+    Sahf,
+    Pushf,
+    Popf,
+    Adc,
+    Sbb,
+    Inc,
+    Dec,
+    Neg,
+    Mul,
+    Imul,
+    Div,
+    Idiv,
+    Aaa,
+    Daa,
+    Aas,
+    Das,
+    Aam,
+    Aad,
+    Cbw,
+    Cwd,
+    Not,
+    Shl,
+    Shr,
+    Sar,
+    Rol,
+    Ror,
+    Rcl,
+    Rcr,
+    And,
+    Test,
+    Or,
+    Xor,
+    Movs,
+    Cmps,
+    Scas,
+    Lods,
+    Stos,
+    Rep,
+    Repe,
+    Repne,
+    Call,
+    Jmp,
+    Ret,
+    Retf,
+    Int,
+    Int3,
+    Into,
+    Iret,
+    Clc,
+    Cmc,
+    Stc,
+    Cld,
+    Std,
+    Cli,
+    Sti,
+    Hlt,
+    Wait,
+    Esc,
+    Lock,
+    Segment,
     Je,
     Jl,
     Jle,
@@ -36,6 +107,76 @@ impl fmt::Display for Op {
             Op::Add => "add",
             Op::Sub => "sub",
             Op::Cmp => "cmp",
+            Op::Push => "push",
+            Op::Pop => "pop",
+            Op::Xchg => "xchg",
+            Op::In => "in",
+            Op::Out => "out",
+            Op::Xlat => "xlat",
+            Op::Lea => "lea",
+            Op::Lds => "lds",
+            Op::Les => "les",
+            Op::Lahf => "lahf",
+            Op::Sahf => "sahf",
+            Op::Pushf => "pushf",
+            Op::Popf => "popf",
+            Op::Adc => "adc",
+            Op::Sbb => "sbb",
+            Op::Inc => "inc",
+            Op::Dec => "dec",
+            Op::Neg => "neg",
+            Op::Mul => "mul",
+            Op::Imul => "imul",
+            Op::Div => "div",
+            Op::Idiv => "idiv",
+            Op::Aaa => "aaa",
+            Op::Daa => "daa",
+            Op::Aas => "aas",
+            Op::Das => "das",
+            Op::Aam => "aam",
+            Op::Aad => "aad",
+            Op::Cbw => "cbw",
+            Op::Cwd => "cwd",
+            Op::Not => "not",
+            Op::Shl => "shl",
+            Op::Shr => "shr",
+            Op::Sar => "sar",
+            Op::Rol => "rol",
+            Op::Ror => "ror",
+            Op::Rcl => "rcl",
+            Op::Rcr => "rcr",
+            Op::And => "and",
+            Op::Test => "test",
+            Op::Or => "or",
+            Op::Xor => "xor",
+            Op::Movs => "movs",
+            Op::Cmps => "cmps",
+            Op::Scas => "scas",
+            Op::Lods => "lods",
+            Op::Stos => "stos",
+            Op::Rep => "rep",
+            Op::Repe => "repe",
+            Op::Repne => "repne",
+            Op::Call => "call",
+            Op::Jmp => "jmp",
+            Op::Ret => "ret",
+            Op::Retf => "retf",
+            Op::Int => "int",
+            Op::Int3 => "int3",
+            Op::Into => "into",
+            Op::Iret => "iret",
+            Op::Clc => "clc",
+            Op::Cmc => "cmc",
+            Op::Stc => "stc",
+            Op::Cld => "cld",
+            Op::Std => "std",
+            Op::Cli => "cli",
+            Op::Sti => "sti",
+            Op::Hlt => "hlt",
+            Op::Wait => "wait",
+            Op::Esc => "esc",
+            Op::Lock => "lock",
+            Op::Segment => "segment",
             Op::Je => "je",
             Op::Jl => "jl",
             Op::Jle => "jle",
@@ -64,7 +205,8 @@ impl fmt::Display for Op {
 }
 
 // Instructions
-pub(crate) enum Instruction {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
     // MOV Instructions
     MovRegisterMemoryToFromRegister,
     MovImmediateToRegisterMemory,
@@ -74,21 +216,169 @@ pub(crate) enum Instruction {
     MovRegisterMemoryToSegmentRegister,
     MovSegmentRegisterToRegisterMemory,
 
+    // PUSH Instructions
+    PushRegisterMemory,
+    PushRegister,
+    PushSegmentRegister,
+
+    // POP Instructions
+    PopRegisterMemory,
+    PopRegister,
+    PopSegmentRegister,
+
+    // XCHG Instructions
+    XchgRegisterMemoryWithRegister,
+    XchgRegisterWithAccumulator,
+
+    // IN Instructions
+    InFixedPort,
+    InVariablePort,
+
+    // OUT Instructions
+    OutFixedPort,
+    OutVariablePort,
+
+    // Other Data Transfer Instructions
+    TranslateByteToAl,
+    LoadEffectiveAddressToRegister,
+    LoadPointerToDs,
+    LoadPointerToEs,
+    LoadAhWithFlags,
+    StoreAhIntoFlags,
+    PushFlags,
+    PopFlags,
+
     // ADD Instructions
     AddRegisterMemoryWithRegisterToEither,
     AddImmediateToRegisterMemory,
     AddImmediateToAccumulator,
 
+    // ADC Instructions
+    AdcRegisterMemoryWithRegisterToEither,
+    AdcImmediateToRegisterMemory,
+    AdcImmediateToAccumulator,
+
+    // INC Instructions
+    IncRegisterMemory,
+    IncRegister,
+
     // SUB Instructions
     SubRegisterMemoryAndRegisterToEither,
     SubImmediateFromRegisterMemory,
     SubImmediateFromAccumulator,
 
+    // SBB Instructions
+    SbbRegisterMemoryAndRegisterToEither,
+    SbbImmediateFromRegisterMemory,
+    SbbImmediateFromAccumulator,
+
+    // DEC Instructions
+    DecRegisterMemory,
+    DecRegister,
+
+    // NEG Instructions
+    NegChangeSign,
+
     // CMP Instructions
     CmpRegisterMemoryAndRegister,
     CmpImmediateWithRegisterMemory,
     CmpImmediateWithAccumulator,
 
+    // MUL, IMUL, DIV, IDIV Instructions
+    MultiplyUnsigned,
+    IntegerMultiplySigned,
+    DivideUnsigned,
+    IntegerDivideSigned,
+
+    // Adjust and Convert Instructions
+    AsciiAdjustForAdd,
+    DecimalAdjustForAdd,
+    AsciiAdjustForSubtract,
+    DecimalAdjustForSubtract,
+    AsciiAdjustForMultiply,
+    AsciiAdjustForDivide,
+    ConvertByteToWord,
+    ConvertWordToDoubleWord,
+
+    // NOT Instructions
+    NotInvert,
+
+    // Shift and Rotate Instructions
+    ShiftLogicalLeft,
+    ShiftLogicalRight,
+    ShiftArithmeticRight,
+    RotateLeft,
+    RotateRight,
+    RotateThroughCarryLeft,
+    RotateThroughCarryRight,
+
+    // AND Instructions
+    AndRegisterMemoryWithRegisterToEither,
+    AndImmediateToRegisterMemory,
+    AndImmediateToAccumulator,
+
+    // TEST Instructions
+    TestRegisterMemoryAndRegister,
+    TestImmediateDataAndRegisterMemory,
+    TestImmediateDataAndAccumulator,
+
+    // OR Instructions
+    OrRegisterMemoryAndRegisterToEither,
+    OrImmediateToRegisterMemory,
+    OrImmediateToAccumulator,
+
+    // XOR Instructions
+    XorRegisterMemoryAndRegisterToEither,
+    XorImmediateToRegisterMemory,
+    XorImmediateToAccumulator,
+
+    // String Manipulation Instructions
+    Repeat,
+    MoveByteOrWord,
+    CompareByteOrWord,
+    ScanByteOrWord,
+    LoadByteOrWordToAlAx,
+    StoreByteOrWordFromAlAx,
+
+    // CALL Instructions
+    CallDirectWithinSegment,
+    CallIndirectWithinSegment,
+    CallDirectIntersegment,
+    CallIndirectIntersegment,
+
+    // JMP Instructions
+    JmpDirectWithinSegment,
+    JmpDirectWithinSegmentShort,
+    JmpIndirectWithinSegment,
+    JmpDirectIntersegment,
+    JmpIndirectIntersegment,
+
+    // RET Instructions
+    RetWithinSegment,
+    RetWithinSegmentAddingImmediateToSp,
+    RetIntersegment,
+    RetIntersegmentAddingImmediateToSp,
+
+    // Interrupt Instructions
+    InterruptTypeSpecified,
+    InterruptType3,
+    InterruptOnOverflow,
+    InterruptReturn,
+
+    // Processor Control Instructions
+    ClearCarry,
+    ComplementCarry,
+    SetCarry,
+    ClearDirection,
+    SetDirection,
+    ClearInterrupt,
+    SetInterrupt,
+    Halt,
+    WaitForTest,
+    EscapeToExternalDevice,
+    BusLockPrefix,
+    SegmentOverridePrefix,
+
     // Jump Instructions
     JumpOnEqual,
     JumpOnLess,
@@ -118,7 +408,8 @@ pub(crate) enum Instruction {
 }
 
 // Addresses
-pub(crate) enum EffectiveAddressCalculation {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EffectiveAddressCalculation {
     AL,   // 000
     CL,   // 001
     DL,   // 010
@@ -177,3 +468,25 @@ impl fmt::Display for EffectiveAddressCalculation {
         write!(f, "{}", value)
     }
 }
+
+// Segment Registers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SegmentRegister {
+    ES, // 00
+    CS, // 01
+    SS, // 10
+    DS, // 11
+}
+
+impl fmt::Display for SegmentRegister {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = match self {
+            SegmentRegister::ES => "es",
+            SegmentRegister::CS => "cs",
+            SegmentRegister::SS => "ss",
+            SegmentRegister::DS => "ds",
+        };
+
+        write!(f, "{}", value)
+    }
+}
@@ -0,0 +1,108 @@
+use crate::decoder::constants::{EffectiveAddressCalculation, Instruction, Op, SegmentRegister};
+use std::fmt;
+
+// Operand Widths
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Width {
+    Byte,
+    Word,
+}
+
+impl fmt::Display for Width {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = match self {
+            Width::Byte => "byte",
+            Width::Word => "word",
+        };
+
+        write!(f, "{}", value)
+    }
+}
+
+// Prefixes
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Prefixes {
+    pub lock: bool,
+    pub repeat: Option<Op>,
+    // The last override wins, as it does on the 8086.
+    pub segment: Option<SegmentRegister>,
+    // The prefix bytes as they were decoded, in order and with any repeats.
+    // Empty when built by hand, which encodes one of each in NASM's order.
+    pub bytes: Vec<u8>,
+}
+
+// Memory Operands
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryAddress {
+    // `None` is a direct address, the displacement is the whole address.
+    pub base: Option<EffectiveAddressCalculation>,
+    pub displacement: i16,
+    pub segment: Option<SegmentRegister>,
+}
+
+// Operands
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operand {
+    Register(EffectiveAddressCalculation),
+    SegmentRegister(SegmentRegister),
+    Memory(MemoryAddress),
+    Immediate(i16),
+    // Signed displacement from the start of the next instruction.
+    Relative(i16),
+    Intersegment { segment: u16, offset: u16 },
+}
+
+// Encoding Choices
+// Where the 8086 has more than one encoding for the same operands, the ones
+// NASM doesn't pick. The default is NASM's choice throughout.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Encoding {
+    // A displacement wider than its value needs: a byte of 0, or a word
+    // that fits a byte.
+    pub displacement: Option<Width>,
+    // The d bit set between two registers, the reg field names the first.
+    pub reversed: bool,
+    // A word immediate the s bit could have carried as one byte.
+    pub long_immediate: bool,
+    // 0x82, the byte immediate group under the s bit.
+    pub alias: bool,
+}
+
+// Decoded Instructions
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodedInstruction {
+    pub op: Op,
+    pub instruction: Instruction,
+    pub operands: Vec<Operand>,
+    pub width: Option<Width>,
+    pub prefixes: Prefixes,
+    pub encoding: Encoding,
+    pub length: usize,
+    pub offset: usize,
+}
+
+impl DecodedInstruction {
+    pub fn new(op: Op, instruction: Instruction, prefixes: Prefixes, offset: usize) -> Self {
+        DecodedInstruction {
+            op,
+            instruction,
+            operands: Vec::new(),
+            width: None,
+            prefixes,
+            encoding: Encoding::default(),
+            length: 0,
+            offset,
+        }
+    }
+
+    /// The absolute offset a relative jump, loop or call lands on. It can
+    /// fall outside the buffer, so it is signed.
+    pub fn target(&self) -> Option<isize> {
+        self.operands.iter().find_map(|operand| match operand {
+            Operand::Relative(displacement) => {
+                Some((self.offset + self.length) as isize + *displacement as isize)
+            }
+            _ => None,
+        })
+    }
+}
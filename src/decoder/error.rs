@@ -0,0 +1,46 @@
+use std::{error::Error, fmt};
+
+// Decode Errors
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    // The input ends before the instruction at `offset` is complete.
+    TruncatedInstruction { offset: usize },
+    // No 8086 instruction is encoded by `opcode` (with its reg field, for
+    // the group opcodes).
+    UndefinedOpcode { offset: usize, opcode: u8 },
+    // The mod and r/m fields name an operand the instruction can't take,
+    // like a register for LEA.
+    InvalidModRm { offset: usize, mod_rm: u8 },
+}
+
+impl DecodeError {
+    pub fn offset(&self) -> usize {
+        match self {
+            DecodeError::TruncatedInstruction { offset }
+            | DecodeError::UndefinedOpcode { offset, .. }
+            | DecodeError::InvalidModRm { offset, .. } => *offset,
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::TruncatedInstruction { offset } => {
+                write!(f, "truncated instruction at offset {}", offset)
+            }
+            DecodeError::UndefinedOpcode { offset, opcode } => {
+                write!(f, "undefined opcode {:#04x} at offset {}", opcode, offset)
+            }
+            DecodeError::InvalidModRm { offset, mod_rm } => {
+                write!(
+                    f,
+                    "invalid mod r/m byte {:#04x} at offset {}",
+                    mod_rm, offset
+                )
+            }
+        }
+    }
+}
+
+impl Error for DecodeError {}
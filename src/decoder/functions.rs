@@ -1,15 +1,40 @@
 pub(crate) mod accumulator_to_memory;
+pub(crate) mod ascii_adjust;
+pub(crate) mod control_transfer;
+pub(crate) mod escape;
 pub(crate) mod immediate_to_accumulator;
 pub(crate) mod immediate_to_register;
 pub(crate) mod immediate_to_register_memory;
+pub(crate) mod input_output;
 pub(crate) mod jumps_and_loops;
 pub(crate) mod memory_to_accumulator;
+pub(crate) mod register;
+pub(crate) mod register_memory;
 pub(crate) mod register_memory_to_from_register;
+pub(crate) mod segment_register;
+pub(crate) mod shifts_and_rotates;
+pub(crate) mod standalone;
+pub(crate) mod string_manipulation;
 
 pub(crate) use crate::decoder::functions::accumulator_to_memory::accumulator_to_memory;
+pub(crate) use crate::decoder::functions::ascii_adjust::ascii_adjust;
+pub(crate) use crate::decoder::functions::control_transfer::{
+    direct_intersegment, direct_within_segment, indirect, interrupt, return_adding_immediate,
+};
+pub(crate) use crate::decoder::functions::escape::escape;
 pub(crate) use crate::decoder::functions::immediate_to_accumulator::immediate_to_accumulator;
 pub(crate) use crate::decoder::functions::immediate_to_register::immediate_to_register;
 pub(crate) use crate::decoder::functions::immediate_to_register_memory::immediate_to_register_memory;
+pub(crate) use crate::decoder::functions::input_output::{fixed_port, variable_port};
 pub(crate) use crate::decoder::functions::jumps_and_loops::{handle_jumps, handle_loops};
 pub(crate) use crate::decoder::functions::memory_to_accumulator::memory_to_accumulator;
-pub(crate) use crate::decoder::functions::register_memory_to_from_register::register_memory_to_from_register;
+pub(crate) use crate::decoder::functions::register::{register, register_with_accumulator};
+pub(crate) use crate::decoder::functions::register_memory::register_memory;
+pub(crate) use crate::decoder::functions::register_memory_to_from_register::{
+    register_memory_to_from_register, register_memory_to_register,
+    segment_register_to_from_register_memory,
+};
+pub(crate) use crate::decoder::functions::segment_register::segment_register;
+pub(crate) use crate::decoder::functions::shifts_and_rotates::shift_rotate;
+pub(crate) use crate::decoder::functions::standalone::standalone;
+pub(crate) use crate::decoder::functions::string_manipulation::string_manipulation;
@@ -0,0 +1,19 @@
+use crate::decoder::{
+    decoded_instruction::{DecodedInstruction, Operand},
+    error::DecodeError,
+    utils::next_byte,
+};
+use std::{iter::Peekable, slice::Iter};
+
+pub(crate) fn ascii_adjust(
+    contents_iterator: &mut Peekable<Iter<'_, u8>>,
+    decoded: &mut DecodedInstruction,
+) -> Result<(), DecodeError> {
+    // AAM and AAD carry the base as a second byte, which is 10 for
+    // everything the mnemonics were designed for.
+    let base = next_byte(contents_iterator, &decoded.offset)?;
+
+    decoded.operands = vec![Operand::Immediate(base as i16)];
+
+    Ok(())
+}
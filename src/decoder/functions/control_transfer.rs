@@ -0,0 +1,97 @@
+use crate::decoder::{
+    constants::Instruction,
+    decoded_instruction::{DecodedInstruction, Operand},
+    error::DecodeError,
+    utils::*,
+};
+use std::{iter::Peekable, slice::Iter};
+
+pub(crate) fn direct_within_segment(
+    contents_iterator: &mut Peekable<Iter<'_, u8>>,
+    instruction: &u8,
+    decoded: &mut DecodedInstruction,
+) -> Result<(), DecodeError> {
+    // The displacement is relative to the next instruction.
+    let data = match instruction {
+        0b11101011 => {
+            let data_field_first = next_byte(contents_iterator, &decoded.offset)?;
+            i8::from_le_bytes([data_field_first]) as i16
+        }
+        _ => {
+            let data_field_first = next_byte(contents_iterator, &decoded.offset)?;
+            let data_field_second = next_byte(contents_iterator, &decoded.offset)?;
+            i16::from_le_bytes([data_field_first, data_field_second])
+        }
+    };
+
+    decoded.operands = vec![Operand::Relative(data)];
+
+    Ok(())
+}
+
+pub(crate) fn direct_intersegment(
+    contents_iterator: &mut Peekable<Iter<'_, u8>>,
+    decoded: &mut DecodedInstruction,
+) -> Result<(), DecodeError> {
+    let offset_field_first = next_byte(contents_iterator, &decoded.offset)?;
+    let offset_field_second = next_byte(contents_iterator, &decoded.offset)?;
+    let segment_field_first = next_byte(contents_iterator, &decoded.offset)?;
+    let segment_field_second = next_byte(contents_iterator, &decoded.offset)?;
+    let offset = u16::from_le_bytes([offset_field_first, offset_field_second]);
+    let segment = u16::from_le_bytes([segment_field_first, segment_field_second]);
+
+    decoded.operands = vec![Operand::Intersegment { segment, offset }];
+
+    Ok(())
+}
+
+pub(crate) fn indirect(
+    contents_iterator: &mut Peekable<Iter<'_, u8>>,
+    decoded: &mut DecodedInstruction,
+) -> Result<(), DecodeError> {
+    let next_instruction = next_byte(contents_iterator, &decoded.offset)?;
+    let mode_field = (next_instruction >> 6) & 0b11;
+    let rm_field = next_instruction & 0b111;
+
+    // A far pointer is 32 bits wide and can only be read from memory.
+    let intersegment = matches!(
+        decoded.instruction,
+        Instruction::CallIndirectIntersegment | Instruction::JmpIndirectIntersegment
+    );
+    if intersegment && mode_field == 0b11 {
+        return Err(DecodeError::InvalidModRm {
+            offset: decoded.offset,
+            mod_rm: next_instruction,
+        });
+    }
+
+    let rm = which_register_memory(contents_iterator, &mode_field, &0b1, &rm_field, decoded)?;
+
+    decoded.operands = vec![rm];
+
+    Ok(())
+}
+
+pub(crate) fn return_adding_immediate(
+    contents_iterator: &mut Peekable<Iter<'_, u8>>,
+    decoded: &mut DecodedInstruction,
+) -> Result<(), DecodeError> {
+    let data_field_first = next_byte(contents_iterator, &decoded.offset)?;
+    let data_field_second = next_byte(contents_iterator, &decoded.offset)?;
+    let data = i16::from_le_bytes([data_field_first, data_field_second]);
+
+    decoded.operands = vec![Operand::Immediate(data)];
+
+    Ok(())
+}
+
+pub(crate) fn interrupt(
+    contents_iterator: &mut Peekable<Iter<'_, u8>>,
+    decoded: &mut DecodedInstruction,
+) -> Result<(), DecodeError> {
+    let data = next_byte(contents_iterator, &decoded.offset)?;
+
+    decoded.operands = vec![Operand::Immediate(data as i16)];
+
+    Ok(())
+}
@@ -0,0 +1,26 @@
+use crate::decoder::{
+    decoded_instruction::{DecodedInstruction, Operand},
+    error::DecodeError,
+    utils::*,
+};
+use std::{iter::Peekable, slice::Iter};
+
+pub(crate) fn escape(
+    contents_iterator: &mut Peekable<Iter<'_, u8>>,
+    instruction: &u8,
+    decoded: &mut DecodedInstruction,
+) -> Result<(), DecodeError> {
+    let next_instruction = next_byte(contents_iterator, &decoded.offset)?;
+    let mode_field = (next_instruction >> 6) & 0b11;
+    let register_field = (next_instruction >> 3) & 0b111;
+    let rm_field = next_instruction & 0b111;
+
+    // The external opcode is split across the low bits of both bytes.
+    let external_opcode = ((instruction & 0b111) << 3) | register_field;
+
+    let rm = which_register_memory(contents_iterator, &mode_field, &0b1, &rm_field, decoded)?;
+
+    decoded.operands = vec![Operand::Immediate(external_opcode as i16), rm];
+
+    Ok(())
+}
@@ -1,40 +1,31 @@
-use crate::decoder::constants::{EffectiveAddressCalculation, Op};
+use crate::decoder::{
+    constants::EffectiveAddressCalculation,
+    decoded_instruction::{DecodedInstruction, Operand},
+    error::DecodeError,
+    utils::*,
+};
 use std::{iter::Peekable, slice::Iter};
 
 pub(crate) fn immediate_to_accumulator(
-    operation: Op,
     contents_iterator: &mut Peekable<Iter<'_, u8>>,
     instruction: &u8,
-    output: &mut String,
-) {
+    decoded: &mut DecodedInstruction,
+) -> Result<(), DecodeError> {
     let word_byte_field = instruction & 0b1;
 
-    if word_byte_field == 0b1 {
-        let data_field_first = *contents_iterator.next().unwrap();
-        let data_field_second = *contents_iterator.next().unwrap();
+    let (accumulator, data) = if word_byte_field == 0b1 {
+        let data_field_first = next_byte(contents_iterator, &decoded.offset)?;
+        let data_field_second = next_byte(contents_iterator, &decoded.offset)?;
         let data = i16::from_le_bytes([data_field_first, data_field_second]);
-        output.push_str(
-            format!(
-                "{} {}, {}\n",
-                operation,
-                EffectiveAddressCalculation::AX,
-                data
-            )
-            .as_str(),
-        );
+        (EffectiveAddressCalculation::AX, data)
     } else {
-        let data_field_first = *contents_iterator.next().unwrap();
-        let data = i8::from_le_bytes([data_field_first]);
-        output.push_str(
-            format!(
-                "{} {}, {}\n",
-                operation,
-                EffectiveAddressCalculation::AL,
-                data
-            )
-            .as_str(),
-        );
-    }
+        let data_field_first = next_byte(contents_iterator, &decoded.offset)?;
+        let data = i8::from_le_bytes([data_field_first]) as i16;
+        (EffectiveAddressCalculation::AL, data)
+    };
 
-    ()
+    decoded.width = Some(which_width(&word_byte_field));
+    decoded.operands = vec![Operand::Register(accumulator), Operand::Immediate(data)];
+
+    Ok(())
 }
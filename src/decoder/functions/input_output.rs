@@ -0,0 +1,42 @@
+use crate::decoder::{
+    constants::{EffectiveAddressCalculation, Op},
+    decoded_instruction::{DecodedInstruction, Operand},
+    error::DecodeError,
+    utils::*,
+};
+use std::{iter::Peekable, slice::Iter};
+
+pub(crate) fn fixed_port(
+    contents_iterator: &mut Peekable<Iter<'_, u8>>,
+    instruction: &u8,
+    decoded: &mut DecodedInstruction,
+) -> Result<(), DecodeError> {
+    let word_byte_field = instruction & 0b1;
+    let port = Operand::Immediate(next_byte(contents_iterator, &decoded.offset)? as i16);
+    let accumulator = Operand::Register(which_register(&word_byte_field, &0b000, &decoded.offset)?);
+
+    decoded.width = Some(which_width(&word_byte_field));
+    decoded.operands = match decoded.op {
+        Op::Out => vec![port, accumulator],
+        _ => vec![accumulator, port],
+    };
+
+    Ok(())
+}
+
+pub(crate) fn variable_port(
+    instruction: &u8,
+    decoded: &mut DecodedInstruction,
+) -> Result<(), DecodeError> {
+    let word_byte_field = instruction & 0b1;
+    let port = Operand::Register(EffectiveAddressCalculation::DX);
+    let accumulator = Operand::Register(which_register(&word_byte_field, &0b000, &decoded.offset)?);
+
+    decoded.width = Some(which_width(&word_byte_field));
+    decoded.operands = match decoded.op {
+        Op::Out => vec![port, accumulator],
+        _ => vec![accumulator, port],
+    };
+
+    Ok(())
+}
@@ -1,24 +1,30 @@
-use crate::decoder::constants::Op;
+use crate::decoder::{
+    decoded_instruction::{DecodedInstruction, Operand},
+    error::DecodeError,
+    utils::next_byte,
+};
 use std::{iter::Peekable, slice::Iter};
 
 pub(crate) fn handle_jumps(
-    operation: Op,
     contents_iterator: &mut Peekable<Iter<'_, u8>>,
-    output: &mut String,
-) {
-    let data_field_first = *contents_iterator.next().unwrap();
+    decoded: &mut DecodedInstruction,
+) -> Result<(), DecodeError> {
+    let data_field_first = next_byte(contents_iterator, &decoded.offset)?;
     let data = i8::from_le_bytes([data_field_first]);
 
-    output.push_str(format!("{} $+{}\n", operation, data).as_str());
+    decoded.operands = vec![Operand::Relative(data as i16)];
+
+    Ok(())
 }
 
 pub(crate) fn handle_loops(
-    operation: Op,
     contents_iterator: &mut Peekable<Iter<'_, u8>>,
-    output: &mut String,
-) {
-    let data_field_first = *contents_iterator.next().unwrap();
+    decoded: &mut DecodedInstruction,
+) -> Result<(), DecodeError> {
+    let data_field_first = next_byte(contents_iterator, &decoded.offset)?;
     let data = i8::from_le_bytes([data_field_first]);
 
-    output.push_str(format!("{} $+{}\n", operation, data).as_str());
+    decoded.operands = vec![Operand::Relative(data as i16)];
+
+    Ok(())
 }
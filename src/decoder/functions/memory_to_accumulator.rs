@@ -1,23 +1,28 @@
-use crate::decoder::{constants::EffectiveAddressCalculation, constants::Op};
+use crate::decoder::{
+    decoded_instruction::{DecodedInstruction, MemoryAddress, Operand},
+    error::DecodeError,
+    utils::*,
+};
 use std::{iter::Peekable, slice::Iter};
 
 pub(crate) fn memory_to_accumulator(
-    operation: Op,
     contents_iterator: &mut Peekable<Iter<'_, u8>>,
-    output: &mut String,
-) {
-    let data_field_first = *contents_iterator.next().unwrap();
-    let data_field_second = *contents_iterator.next().unwrap();
+    instruction: &u8,
+    decoded: &mut DecodedInstruction,
+) -> Result<(), DecodeError> {
+    let word_byte_field = instruction & 0b1;
+    let data_field_first = next_byte(contents_iterator, &decoded.offset)?;
+    let data_field_second = next_byte(contents_iterator, &decoded.offset)?;
     let data = i16::from_le_bytes([data_field_first, data_field_second]);
-    output.push_str(
-        format!(
-            "{} {}, [{}]\n",
-            operation,
-            EffectiveAddressCalculation::AX,
-            data
-        )
-        .as_str(),
-    );
+    let accumulator = which_register(&word_byte_field, &0b000, &decoded.offset)?;
+    let address = MemoryAddress {
+        base: None,
+        displacement: data,
+        segment: decoded.prefixes.segment,
+    };
 
-    ()
+    decoded.width = Some(which_width(&word_byte_field));
+    decoded.operands = vec![Operand::Register(accumulator), Operand::Memory(address)];
+
+    Ok(())
 }
@@ -0,0 +1,35 @@
+use crate::decoder::{
+    constants::EffectiveAddressCalculation,
+    decoded_instruction::{DecodedInstruction, Operand, Width},
+    error::DecodeError,
+    utils::*,
+};
+
+pub(crate) fn register(
+    instruction: &u8,
+    decoded: &mut DecodedInstruction,
+) -> Result<(), DecodeError> {
+    let register_field = instruction & 0b111;
+    let reg = which_register(&0b1, &register_field, &decoded.offset)?;
+
+    decoded.width = Some(Width::Word);
+    decoded.operands = vec![Operand::Register(reg)];
+
+    Ok(())
+}
+
+pub(crate) fn register_with_accumulator(
+    instruction: &u8,
+    decoded: &mut DecodedInstruction,
+) -> Result<(), DecodeError> {
+    let register_field = instruction & 0b111;
+    let reg = which_register(&0b1, &register_field, &decoded.offset)?;
+
+    decoded.width = Some(Width::Word);
+    decoded.operands = vec![
+        Operand::Register(EffectiveAddressCalculation::AX),
+        Operand::Register(reg),
+    ];
+
+    Ok(())
+}
@@ -0,0 +1,26 @@
+use crate::decoder::{decoded_instruction::DecodedInstruction, error::DecodeError, utils::*};
+use std::{iter::Peekable, slice::Iter};
+
+pub(crate) fn register_memory(
+    contents_iterator: &mut Peekable<Iter<'_, u8>>,
+    instruction: &u8,
+    decoded: &mut DecodedInstruction,
+) -> Result<(), DecodeError> {
+    let next_instruction = next_byte(contents_iterator, &decoded.offset)?;
+    let word_byte_field = instruction & 0b1;
+    let mode_field = (next_instruction >> 6) & 0b11;
+    let rm_field = next_instruction & 0b111;
+
+    let rm = which_register_memory(
+        contents_iterator,
+        &mode_field,
+        &word_byte_field,
+        &rm_field,
+        decoded,
+    )?;
+
+    decoded.width = Some(which_width(&word_byte_field));
+    decoded.operands = vec![rm];
+
+    Ok(())
+}
@@ -1,66 +1,36 @@
-use crate::decoder::{constants::Op, utils::*};
+use crate::decoder::{
+    constants::Op,
+    decoded_instruction::{DecodedInstruction, Operand, Width},
+    error::DecodeError,
+    utils::*,
+};
 use std::{iter::Peekable, slice::Iter};
 
 pub(crate) fn register_memory_to_from_register(
-    operation: Op,
     contents_iterator: &mut Peekable<Iter<'_, u8>>,
     instruction: &u8,
-    output: &mut String,
-) -> () {
-    let rm: String;
-
-    let next_instruction = contents_iterator.next().unwrap();
+    decoded: &mut DecodedInstruction,
+) -> Result<(), DecodeError> {
+    let next_instruction = next_byte(contents_iterator, &decoded.offset)?;
     let direction_field = (instruction >> 1) & 0b1;
     let word_byte_field = instruction & 0b1;
     let mode_field = (next_instruction >> 6) & 0b11;
     let register_field = (next_instruction >> 3) & 0b111;
     let rm_field = next_instruction & 0b111;
-    let displacement = which_displacement(&rm_field, &mode_field);
-
-    let reg = which_address(&mode_field, &word_byte_field, &register_field, &true)
-        .unwrap()
-        .to_string();
-
-    let rm_address_calculation = which_address(&mode_field, &word_byte_field, &rm_field, &false);
-
-    if mode_field != 0b11 {
-        rm = match displacement {
-            1 => {
-                let displacement_low = *contents_iterator.next().unwrap();
-                let displacement = i8::from_le_bytes([displacement_low]);
-
-                if let Some(rm_address_calculation) = rm_address_calculation {
-                    if displacement.is_negative() {
-                        format!("[{} - {}]", rm_address_calculation, -displacement)
-                    } else if displacement.is_positive() {
-                        format!("[{} + {}]", rm_address_calculation, displacement)
-                    } else {
-                        format!("[{}]", rm_address_calculation)
-                    }
-                } else {
-                    format!("[{}]", displacement)
-                }
-            }
-            2 => {
-                let displacement_low = *contents_iterator.next().unwrap();
-                let displacement_high = *contents_iterator.next().unwrap();
-                let displacement = i16::from_le_bytes([displacement_low, displacement_high]);
-
-                if let Some(rm_address_calculation) = rm_address_calculation {
-                    if displacement.is_negative() {
-                        format!("[{} - {}]", rm_address_calculation, -displacement)
-                    } else {
-                        format!("[{} + {}]", rm_address_calculation, displacement)
-                    }
-                } else {
-                    format!("[{}]", displacement)
-                }
-            }
-            _ => format!("[{}]", rm_address_calculation.unwrap()),
-        };
-    } else {
-        rm = format!("{}", rm_address_calculation.unwrap());
-    }
+
+    let reg = Operand::Register(which_register(
+        &word_byte_field,
+        &register_field,
+        &decoded.offset,
+    )?);
+
+    let rm = which_register_memory(
+        contents_iterator,
+        &mode_field,
+        &word_byte_field,
+        &rm_field,
+        decoded,
+    )?;
 
     let (destination, source) = if direction_field == 1 {
         (reg, rm)
@@ -68,14 +38,86 @@ pub(crate) fn register_memory_to_from_register(
         (rm, reg)
     };
 
-    let line: Option<String> = match displacement {
-        0 | 1 | 2 => Some(format!("{} {}, {}\n", operation, destination, source)),
-        _ => None,
-    };
+    // XCHG sets the bit in its opcode, it means nothing there.
+    decoded.encoding.reversed =
+        direction_field == 1 && mode_field == 0b11 && decoded.op != Op::Xchg;
+
+    decoded.width = Some(which_width(&word_byte_field));
+    decoded.operands = vec![destination, source];
+
+    Ok(())
+}
+
+pub(crate) fn segment_register_to_from_register_memory(
+    contents_iterator: &mut Peekable<Iter<'_, u8>>,
+    instruction: &u8,
+    decoded: &mut DecodedInstruction,
+) -> Result<(), DecodeError> {
+    let next_instruction = next_byte(contents_iterator, &decoded.offset)?;
+    let direction_field = (instruction >> 1) & 0b1;
+    // Segment registers are always 16-bit, the w bit is not present.
+    let word_byte_field = 0b1;
+    let mode_field = (next_instruction >> 6) & 0b11;
+    let segment_register_field = (next_instruction >> 3) & 0b11;
+    let rm_field = next_instruction & 0b111;
+
+    let sr = Operand::SegmentRegister(which_segment_register(&segment_register_field));
 
-    if line.is_some() {
-        output.push_str(line.unwrap().as_str());
+    let rm = which_register_memory(
+        contents_iterator,
+        &mode_field,
+        &word_byte_field,
+        &rm_field,
+        decoded,
+    )?;
+
+    let (destination, source) = if direction_field == 1 {
+        (sr, rm)
+    } else {
+        (rm, sr)
     };
 
-    ()
+    decoded.width = Some(Width::Word);
+    decoded.operands = vec![destination, source];
+
+    Ok(())
+}
+
+pub(crate) fn register_memory_to_register(
+    contents_iterator: &mut Peekable<Iter<'_, u8>>,
+    decoded: &mut DecodedInstruction,
+) -> Result<(), DecodeError> {
+    let next_instruction = next_byte(contents_iterator, &decoded.offset)?;
+    // LEA, LDS and LES always load a 16-bit register, there is no d or w bit.
+    let word_byte_field = 0b1;
+    let mode_field = (next_instruction >> 6) & 0b11;
+    let register_field = (next_instruction >> 3) & 0b111;
+    let rm_field = next_instruction & 0b111;
+
+    // Loading an address out of a register has no encoding.
+    if mode_field == 0b11 {
+        return Err(DecodeError::InvalidModRm {
+            offset: decoded.offset,
+            mod_rm: next_instruction,
+        });
+    }
+
+    let reg = Operand::Register(which_register(
+        &word_byte_field,
+        &register_field,
+        &decoded.offset,
+    )?);
+
+    let rm = which_register_memory(
+        contents_iterator,
+        &mode_field,
+        &word_byte_field,
+        &rm_field,
+        decoded,
+    )?;
+
+    decoded.width = Some(Width::Word);
+    decoded.operands = vec![reg, rm];
+
+    Ok(())
 }
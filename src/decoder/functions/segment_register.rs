@@ -0,0 +1,18 @@
+use crate::decoder::{
+    decoded_instruction::{DecodedInstruction, Operand, Width},
+    error::DecodeError,
+    utils::*,
+};
+
+pub(crate) fn segment_register(
+    instruction: &u8,
+    decoded: &mut DecodedInstruction,
+) -> Result<(), DecodeError> {
+    let segment_register_field = (instruction >> 3) & 0b11;
+    let sr = which_segment_register(&segment_register_field);
+
+    decoded.width = Some(Width::Word);
+    decoded.operands = vec![Operand::SegmentRegister(sr)];
+
+    Ok(())
+}
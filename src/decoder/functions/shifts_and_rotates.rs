@@ -0,0 +1,39 @@
+use crate::decoder::{
+    constants::EffectiveAddressCalculation,
+    decoded_instruction::{DecodedInstruction, Operand},
+    error::DecodeError,
+    utils::*,
+};
+use std::{iter::Peekable, slice::Iter};
+
+pub(crate) fn shift_rotate(
+    contents_iterator: &mut Peekable<Iter<'_, u8>>,
+    instruction: &u8,
+    decoded: &mut DecodedInstruction,
+) -> Result<(), DecodeError> {
+    let next_instruction = next_byte(contents_iterator, &decoded.offset)?;
+    let variable_field = (instruction >> 1) & 0b1;
+    let word_byte_field = instruction & 0b1;
+    let mode_field = (next_instruction >> 6) & 0b11;
+    let rm_field = next_instruction & 0b111;
+
+    let rm = which_register_memory(
+        contents_iterator,
+        &mode_field,
+        &word_byte_field,
+        &rm_field,
+        decoded,
+    )?;
+
+    // v = 0 shifts by one, v = 1 shifts by the count in CL.
+    let count = if variable_field == 0b1 {
+        Operand::Register(EffectiveAddressCalculation::CL)
+    } else {
+        Operand::Immediate(1)
+    };
+
+    decoded.width = Some(which_width(&word_byte_field));
+    decoded.operands = vec![rm, count];
+
+    Ok(())
+}
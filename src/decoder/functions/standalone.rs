@@ -0,0 +1,7 @@
+use crate::decoder::{decoded_instruction::DecodedInstruction, error::DecodeError};
+
+pub(crate) fn standalone(decoded: &mut DecodedInstruction) -> Result<(), DecodeError> {
+    decoded.operands = Vec::new();
+
+    Ok(())
+}
@@ -0,0 +1,13 @@
+use crate::decoder::{decoded_instruction::DecodedInstruction, error::DecodeError, utils::*};
+
+pub(crate) fn string_manipulation(
+    instruction: &u8,
+    decoded: &mut DecodedInstruction,
+) -> Result<(), DecodeError> {
+    let word_byte_field = instruction & 0b1;
+
+    decoded.width = Some(which_width(&word_byte_field));
+    decoded.operands = Vec::new();
+
+    Ok(())
+}
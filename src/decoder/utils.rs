@@ -1,4 +1,7 @@
 use crate::decoder::constants::*;
+use crate::decoder::decoded_instruction::{DecodedInstruction, MemoryAddress, Operand, Width};
+use crate::decoder::error::DecodeError;
+use std::{iter::Peekable, slice::Iter};
 
 pub(crate) fn which_instruction(byte: &u8) -> (Op, Instruction) {
     match byte {
@@ -11,28 +14,160 @@ pub(crate) fn which_instruction(byte: &u8) -> (Op, Instruction) {
         0b10001110 => (Op::Mov, Instruction::MovRegisterMemoryToSegmentRegister),
         0b10001100 => (Op::Mov, Instruction::MovSegmentRegisterToRegisterMemory),
 
+        // PUSH
+        0b01010000..=0b01010111 => (Op::Push, Instruction::PushRegister),
+        0b00000110 | 0b00001110 | 0b00010110 | 0b00011110 => {
+            (Op::Push, Instruction::PushSegmentRegister)
+        }
+
+        // POP
+        0b10001111 => (Op::Pop, Instruction::PopRegisterMemory),
+        0b01011000..=0b01011111 => (Op::Pop, Instruction::PopRegister),
+        0b00000111 | 0b00001111 | 0b00010111 | 0b00011111 => {
+            (Op::Pop, Instruction::PopSegmentRegister)
+        }
+
+        // XCHG
+        0b10000110..=0b10000111 => (Op::Xchg, Instruction::XchgRegisterMemoryWithRegister),
+        0b10010000..=0b10010111 => (Op::Xchg, Instruction::XchgRegisterWithAccumulator),
+
+        // IN
+        0b11100100..=0b11100101 => (Op::In, Instruction::InFixedPort),
+        0b11101100..=0b11101101 => (Op::In, Instruction::InVariablePort),
+
+        // OUT
+        0b11100110..=0b11100111 => (Op::Out, Instruction::OutFixedPort),
+        0b11101110..=0b11101111 => (Op::Out, Instruction::OutVariablePort),
+
+        // XLAT, LEA, LDS, LES, LAHF, SAHF, PUSHF, POPF
+        0b11010111 => (Op::Xlat, Instruction::TranslateByteToAl),
+        0b10001101 => (Op::Lea, Instruction::LoadEffectiveAddressToRegister),
+        0b11000101 => (Op::Lds, Instruction::LoadPointerToDs),
+        0b11000100 => (Op::Les, Instruction::LoadPointerToEs),
+        0b10011111 => (Op::Lahf, Instruction::LoadAhWithFlags),
+        0b10011110 => (Op::Sahf, Instruction::StoreAhIntoFlags),
+        0b10011100 => (Op::Pushf, Instruction::PushFlags),
+        0b10011101 => (Op::Popf, Instruction::PopFlags),
+
         // ADD
         0b00000000..=0b00000011 => (Op::Add, Instruction::AddRegisterMemoryWithRegisterToEither),
         0b00000100..=0b00000101 => (Op::Add, Instruction::AddImmediateToAccumulator),
 
+        // ADC
+        0b00010000..=0b00010011 => (Op::Adc, Instruction::AdcRegisterMemoryWithRegisterToEither),
+        0b00010100..=0b00010101 => (Op::Adc, Instruction::AdcImmediateToAccumulator),
+
+        // INC
+        0b01000000..=0b01000111 => (Op::Inc, Instruction::IncRegister),
+
+        // AAA, DAA
+        0b00110111 => (Op::Aaa, Instruction::AsciiAdjustForAdd),
+        0b00100111 => (Op::Daa, Instruction::DecimalAdjustForAdd),
+
         // SUB
         0b00101000..=0b00101011 => (Op::Sub, Instruction::SubRegisterMemoryAndRegisterToEither),
         0b00101100..=0b00101101 => (Op::Sub, Instruction::SubImmediateFromAccumulator),
 
+        // SBB
+        0b00011000..=0b00011011 => (Op::Sbb, Instruction::SbbRegisterMemoryAndRegisterToEither),
+        0b00011100..=0b00011101 => (Op::Sbb, Instruction::SbbImmediateFromAccumulator),
+
+        // DEC
+        0b01001000..=0b01001111 => (Op::Dec, Instruction::DecRegister),
+
         // CMP
         0b00111000..=0b00111011 => (Op::Cmp, Instruction::CmpRegisterMemoryAndRegister),
         0b00111100..=0b00111101 => (Op::Cmp, Instruction::CmpImmediateWithAccumulator),
 
+        // AAS, DAS, AAM, AAD, CBW, CWD
+        0b00111111 => (Op::Aas, Instruction::AsciiAdjustForSubtract),
+        0b00101111 => (Op::Das, Instruction::DecimalAdjustForSubtract),
+        0b11010100 => (Op::Aam, Instruction::AsciiAdjustForMultiply),
+        0b11010101 => (Op::Aad, Instruction::AsciiAdjustForDivide),
+        0b10011000 => (Op::Cbw, Instruction::ConvertByteToWord),
+        0b10011001 => (Op::Cwd, Instruction::ConvertWordToDoubleWord),
+
+        // AND
+        0b00100000..=0b00100011 => (Op::And, Instruction::AndRegisterMemoryWithRegisterToEither),
+        0b00100100..=0b00100101 => (Op::And, Instruction::AndImmediateToAccumulator),
+
+        // TEST
+        0b10000100..=0b10000101 => (Op::Test, Instruction::TestRegisterMemoryAndRegister),
+        0b10101000..=0b10101001 => (Op::Test, Instruction::TestImmediateDataAndAccumulator),
+
+        // OR
+        0b00001000..=0b00001011 => (Op::Or, Instruction::OrRegisterMemoryAndRegisterToEither),
+        0b00001100..=0b00001101 => (Op::Or, Instruction::OrImmediateToAccumulator),
+
+        // XOR
+        0b00110000..=0b00110011 => (Op::Xor, Instruction::XorRegisterMemoryAndRegisterToEither),
+        0b00110100..=0b00110101 => (Op::Xor, Instruction::XorImmediateToAccumulator),
+
+        // MOVS, CMPS, SCAS, LODS, STOS
+        0b10100100..=0b10100101 => (Op::Movs, Instruction::MoveByteOrWord),
+        0b10100110..=0b10100111 => (Op::Cmps, Instruction::CompareByteOrWord),
+        0b10101110..=0b10101111 => (Op::Scas, Instruction::ScanByteOrWord),
+        0b10101100..=0b10101101 => (Op::Lods, Instruction::LoadByteOrWordToAlAx),
+        0b10101010..=0b10101011 => (Op::Stos, Instruction::StoreByteOrWordFromAlAx),
+
+        // CALL
+        0b11101000 => (Op::Call, Instruction::CallDirectWithinSegment),
+        0b10011010 => (Op::Call, Instruction::CallDirectIntersegment),
+
+        // JMP
+        0b11101001 => (Op::Jmp, Instruction::JmpDirectWithinSegment),
+        0b11101011 => (Op::Jmp, Instruction::JmpDirectWithinSegmentShort),
+        0b11101010 => (Op::Jmp, Instruction::JmpDirectIntersegment),
+
+        // RET
+        0b11000011 => (Op::Ret, Instruction::RetWithinSegment),
+        0b11000010 => (Op::Ret, Instruction::RetWithinSegmentAddingImmediateToSp),
+        0b11001011 => (Op::Retf, Instruction::RetIntersegment),
+        0b11001010 => (Op::Retf, Instruction::RetIntersegmentAddingImmediateToSp),
+
+        // INT, INTO, IRET
+        0b11001101 => (Op::Int, Instruction::InterruptTypeSpecified),
+        0b11001100 => (Op::Int3, Instruction::InterruptType3),
+        0b11001110 => (Op::Into, Instruction::InterruptOnOverflow),
+        0b11001111 => (Op::Iret, Instruction::InterruptReturn),
+
+        // CLC, CMC, STC, CLD, STD, CLI, STI, HLT, WAIT, ESC, LOCK
+        0b11111000 => (Op::Clc, Instruction::ClearCarry),
+        0b11110101 => (Op::Cmc, Instruction::ComplementCarry),
+        0b11111001 => (Op::Stc, Instruction::SetCarry),
+        0b11111100 => (Op::Cld, Instruction::ClearDirection),
+        0b11111101 => (Op::Std, Instruction::SetDirection),
+        0b11111010 => (Op::Cli, Instruction::ClearInterrupt),
+        0b11111011 => (Op::Sti, Instruction::SetInterrupt),
+        0b11110100 => (Op::Hlt, Instruction::Halt),
+        0b10011011 => (Op::Wait, Instruction::WaitForTest),
+        0b11011000..=0b11011111 => (Op::Esc, Instruction::EscapeToExternalDevice),
+        0b11110000 => (Op::Lock, Instruction::BusLockPrefix),
+
+        // SEGMENT
+        0b00100110 | 0b00101110 | 0b00110110 | 0b00111110 => {
+            (Op::Segment, Instruction::SegmentOverridePrefix)
+        }
+
         _ => (Op::Invalid, Instruction::Invalid),
     }
 }
 
+// Lock, the repeat prefixes and the segment overrides.
+pub(crate) fn is_prefix(byte: &u8) -> bool {
+    matches!(
+        byte,
+        0b11110000 | 0b11110010 | 0b11110011 | 0b00100110 | 0b00101110 | 0b00110110 | 0b00111110
+    )
+}
+
 pub(crate) fn which_address(
     mode_field: &u8,
     word_byte_field: &u8,
     rm_field: &u8,
     register_only: &bool,
-) -> Option<EffectiveAddressCalculation> {
+    offset: &usize,
+) -> Result<Option<EffectiveAddressCalculation>, DecodeError> {
     let address: Option<EffectiveAddressCalculation> =
         match (mode_field, word_byte_field, rm_field, register_only) {
             // In Instruction::ImmediateToRegister, mode_field is not present.
@@ -68,16 +203,52 @@ pub(crate) fn which_address(
                 }
             }
             (_, _, 0b111, false) => Some(EffectiveAddressCalculation::Bx),
-            _ => panic!(
-                "Invalid {:08b}, {:08b}, {:08b}, {:}",
-                mode_field, word_byte_field, rm_field, register_only
-            ),
+            _ => {
+                return Err(DecodeError::InvalidModRm {
+                    offset: *offset,
+                    mod_rm: (mode_field << 6) | (rm_field & 0b111),
+                })
+            }
         };
 
-    address
+    Ok(address)
 }
 
-pub(crate) fn which_displacement(rm_field: &u8, mode_field: &u8) -> u8 {
+pub(crate) fn which_register(
+    word_byte_field: &u8,
+    register_field: &u8,
+    offset: &usize,
+) -> Result<EffectiveAddressCalculation, DecodeError> {
+    which_address(&0b11, word_byte_field, register_field, &true, offset)?.ok_or(
+        DecodeError::InvalidModRm {
+            offset: *offset,
+            mod_rm: 0b11000000 | (register_field & 0b111),
+        },
+    )
+}
+
+pub(crate) fn which_segment_register(register_field: &u8) -> SegmentRegister {
+    match register_field & 0b11 {
+        0b00 => SegmentRegister::ES,
+        0b01 => SegmentRegister::CS,
+        0b10 => SegmentRegister::SS,
+        _ => SegmentRegister::DS,
+    }
+}
+
+pub(crate) fn which_width(word_byte_field: &u8) -> Width {
+    if *word_byte_field == 0b1 {
+        Width::Word
+    } else {
+        Width::Byte
+    }
+}
+
+pub(crate) fn which_displacement(
+    rm_field: &u8,
+    mode_field: &u8,
+    offset: &usize,
+) -> Result<u8, DecodeError> {
     let displacement: u8 = match mode_field {
         0b00 => {
             if *rm_field == 0b110 {
@@ -89,8 +260,74 @@ pub(crate) fn which_displacement(rm_field: &u8, mode_field: &u8) -> u8 {
         0b01 => 1,
         0b10 => 2,
         0b11 => 0,
-        _ => panic!("Invalid mode_field: {:b}", mode_field),
+        _ => {
+            return Err(DecodeError::InvalidModRm {
+                offset: *offset,
+                mod_rm: (mode_field << 6) | (rm_field & 0b111),
+            })
+        }
+    };
+
+    Ok(displacement)
+}
+
+pub(crate) fn next_byte(
+    contents_iterator: &mut Peekable<Iter<'_, u8>>,
+    offset: &usize,
+) -> Result<u8, DecodeError> {
+    contents_iterator
+        .next()
+        .copied()
+        .ok_or(DecodeError::TruncatedInstruction { offset: *offset })
+}
+
+pub(crate) fn which_register_memory(
+    contents_iterator: &mut Peekable<Iter<'_, u8>>,
+    mode_field: &u8,
+    word_byte_field: &u8,
+    rm_field: &u8,
+    decoded: &mut DecodedInstruction,
+) -> Result<Operand, DecodeError> {
+    let offset = &decoded.offset;
+    let displacement = which_displacement(rm_field, mode_field, offset)?;
+    let rm_address_calculation =
+        which_address(mode_field, word_byte_field, rm_field, &false, offset)?;
+
+    if *mode_field == 0b11 {
+        return Ok(Operand::Register(which_register(
+            word_byte_field,
+            rm_field,
+            offset,
+        )?));
+    }
+
+    let displacement = match displacement {
+        1 => {
+            let displacement_low = next_byte(contents_iterator, offset)?;
+            i8::from_le_bytes([displacement_low]) as i16
+        }
+        2 => {
+            let displacement_low = next_byte(contents_iterator, offset)?;
+            let displacement_high = next_byte(contents_iterator, offset)?;
+            i16::from_le_bytes([displacement_low, displacement_high])
+        }
+        _ => 0,
+    };
+
+    // The encoder would otherwise shorten the displacement.
+    decoded.encoding.displacement = match mode_field {
+        0b01 if displacement == 0
+            && rm_address_calculation != Some(EffectiveAddressCalculation::Bp) =>
+        {
+            Some(Width::Byte)
+        }
+        0b10 if i8::try_from(displacement).is_ok() => Some(Width::Word),
+        _ => None,
     };
 
-    displacement
+    Ok(Operand::Memory(MemoryAddress {
+        base: rm_address_calculation,
+        displacement,
+        segment: decoded.prefixes.segment,
+    }))
 }
@@ -0,0 +1,475 @@
+pub mod error;
+pub(crate) mod utils;
+
+use crate::decoder::constants::{EffectiveAddressCalculation, Instruction, SegmentRegister};
+use crate::decoder::decoded_instruction::{DecodedInstruction, Operand};
+use error::EncodeError;
+use utils::*;
+
+fn which_segment_override(decoded: &DecodedInstruction) -> Option<SegmentRegister> {
+    // A hand-built instruction may only name the segment on its memory operand.
+    decoded.prefixes.segment.or_else(|| {
+        decoded.operands.iter().find_map(|operand| match operand {
+            Operand::Memory(address) => address.segment,
+            _ => None,
+        })
+    })
+}
+
+/// Encodes one instruction into its machine bytes, prefixes included. Where
+/// the 8086 has more than one encoding, the one NASM picks is used unless
+/// `decoded.encoding` records another: d = 0 between two registers, the
+/// sign-extended immediate when it fits and the shortest displacement.
+pub fn encode(decoded: &DecodedInstruction) -> Result<Vec<u8>, EncodeError> {
+    let instruction = &decoded.instruction;
+    let operands = decoded.operands.as_slice();
+    let invalid = EncodeError::InvalidOperands {
+        instruction: *instruction,
+    };
+
+    let (opcode, group_field) = which_opcode(instruction).ok_or(EncodeError::NoEncoding {
+        instruction: *instruction,
+    })?;
+
+    let mut bytes: Vec<u8> = Vec::new();
+
+    // Decoded prefixes go back as they came, out of order or repeated.
+    if decoded.prefixes.bytes.is_empty() {
+        encode_prefixes(
+            &mut bytes,
+            &decoded.prefixes,
+            &which_segment_override(decoded),
+        );
+    } else {
+        bytes.extend(&decoded.prefixes.bytes);
+    }
+
+    match instruction {
+        Instruction::MovRegisterMemoryToFromRegister
+        | Instruction::XchgRegisterMemoryWithRegister
+        | Instruction::AddRegisterMemoryWithRegisterToEither
+        | Instruction::AdcRegisterMemoryWithRegisterToEither
+        | Instruction::SubRegisterMemoryAndRegisterToEither
+        | Instruction::SbbRegisterMemoryAndRegisterToEither
+        | Instruction::CmpRegisterMemoryAndRegister
+        | Instruction::AndRegisterMemoryWithRegisterToEither
+        | Instruction::TestRegisterMemoryAndRegister
+        | Instruction::OrRegisterMemoryAndRegisterToEither
+        | Instruction::XorRegisterMemoryAndRegisterToEither => {
+            let xchg = *instruction == Instruction::XchgRegisterMemoryWithRegister;
+
+            let (direction_field, reg, rm) = match operands {
+                // XCHG always decodes with the register first.
+                [Operand::Register(reg), rm] if xchg => (0b0, reg, rm),
+                [rm @ Operand::Memory(_), Operand::Register(reg)] if xchg => (0b0, reg, rm),
+                [Operand::Register(reg), rm @ Operand::Memory(_)] => (0b1, reg, rm),
+                [Operand::Register(reg), rm] if decoded.encoding.reversed => (0b1, reg, rm),
+                [rm, Operand::Register(reg)] => (0b0, reg, rm),
+                _ => return Err(invalid),
+            };
+
+            // TEST and XCHG have no d bit, the operands commute.
+            let direction_field = match instruction {
+                Instruction::TestRegisterMemoryAndRegister
+                | Instruction::XchgRegisterMemoryWithRegister => 0b0,
+                _ => direction_field,
+            };
+
+            let (register_field, word_byte_field) = which_register_field(reg).ok_or(invalid)?;
+
+            // Both registers have to be the same width.
+            if let Operand::Register(rm) = rm {
+                match which_register_field(rm) {
+                    Some((_, rm_word_byte_field)) if rm_word_byte_field == word_byte_field => {}
+                    _ => return Err(invalid),
+                }
+            }
+
+            bytes.push(opcode | (direction_field << 1) | word_byte_field);
+            encode_register_memory(
+                &mut bytes,
+                instruction,
+                &register_field,
+                rm,
+                &decoded.encoding.displacement,
+            )?;
+        }
+        Instruction::MovRegisterMemoryToSegmentRegister => match operands {
+            [Operand::SegmentRegister(sr), rm] => {
+                bytes.push(opcode);
+                encode_register_memory(
+                    &mut bytes,
+                    instruction,
+                    &which_segment_register_field(sr),
+                    rm,
+                    &decoded.encoding.displacement,
+                )?;
+            }
+            _ => return Err(invalid),
+        },
+        Instruction::MovSegmentRegisterToRegisterMemory => match operands {
+            [rm, Operand::SegmentRegister(sr)] => {
+                bytes.push(opcode);
+                encode_register_memory(
+                    &mut bytes,
+                    instruction,
+                    &which_segment_register_field(sr),
+                    rm,
+                    &decoded.encoding.displacement,
+                )?;
+            }
+            _ => return Err(invalid),
+        },
+        Instruction::LoadEffectiveAddressToRegister
+        | Instruction::LoadPointerToDs
+        | Instruction::LoadPointerToEs => match operands {
+            [Operand::Register(reg), rm @ Operand::Memory(_)] => {
+                bytes.push(opcode);
+                encode_register_memory(
+                    &mut bytes,
+                    instruction,
+                    &register_field(instruction, reg)?,
+                    rm,
+                    &decoded.encoding.displacement,
+                )?;
+            }
+            _ => return Err(invalid),
+        },
+        Instruction::MovImmediateToRegisterMemory
+        | Instruction::AddImmediateToRegisterMemory
+        | Instruction::AdcImmediateToRegisterMemory
+        | Instruction::SubImmediateFromRegisterMemory
+        | Instruction::SbbImmediateFromRegisterMemory
+        | Instruction::CmpImmediateWithRegisterMemory
+        | Instruction::AndImmediateToRegisterMemory
+        | Instruction::TestImmediateDataAndRegisterMemory
+        | Instruction::OrImmediateToRegisterMemory
+        | Instruction::XorImmediateToRegisterMemory => match operands {
+            [rm, Operand::Immediate(data)] => {
+                let word_byte_field =
+                    which_word_byte_field(instruction, &decoded.width, &operands[..1])?;
+
+                // MOV and TEST have no s bit. The others sign-extend a byte
+                // whenever the word value fits in one.
+                let sign_field = match instruction {
+                    Instruction::MovImmediateToRegisterMemory
+                    | Instruction::TestImmediateDataAndRegisterMemory => 0b0,
+                    _ if word_byte_field == 0b0 && decoded.encoding.alias => 0b1,
+                    _ if word_byte_field == 0b1
+                        && i8::try_from(*data).is_ok()
+                        && !decoded.encoding.long_immediate =>
+                    {
+                        0b1
+                    }
+                    _ => 0b0,
+                };
+
+                bytes.push(opcode | (sign_field << 1) | word_byte_field);
+                encode_register_memory(
+                    &mut bytes,
+                    instruction,
+                    &group_field.unwrap_or_default(),
+                    rm,
+                    &decoded.encoding.displacement,
+                )?;
+                encode_immediate(
+                    &mut bytes,
+                    instruction,
+                    &(word_byte_field & !sign_field),
+                    data,
+                )?;
+            }
+            _ => return Err(invalid),
+        },
+        Instruction::MovImmediateToRegister => match operands {
+            [Operand::Register(reg), Operand::Immediate(data)] => {
+                let (register_field, word_byte_field) = which_register_field(reg).ok_or(invalid)?;
+
+                bytes.push(opcode | (word_byte_field << 3) | register_field);
+                encode_immediate(&mut bytes, instruction, &word_byte_field, data)?;
+            }
+            _ => return Err(invalid),
+        },
+        Instruction::MovMemoryToAccumulator | Instruction::MovAccumulatorToMemory => {
+            let (accumulator, address) = match (instruction, operands) {
+                (
+                    Instruction::MovMemoryToAccumulator,
+                    [Operand::Register(accumulator), Operand::Memory(address)],
+                )
+                | (
+                    Instruction::MovAccumulatorToMemory,
+                    [Operand::Memory(address), Operand::Register(accumulator)],
+                ) => (accumulator, address),
+                _ => return Err(invalid),
+            };
+
+            let word_byte_field = match (which_register_field(accumulator), address.base) {
+                (Some((0b000, word_byte_field)), None) => word_byte_field,
+                _ => return Err(invalid),
+            };
+
+            bytes.push(opcode | word_byte_field);
+            bytes.extend(address.displacement.to_le_bytes());
+        }
+        Instruction::AddImmediateToAccumulator
+        | Instruction::AdcImmediateToAccumulator
+        | Instruction::SubImmediateFromAccumulator
+        | Instruction::SbbImmediateFromAccumulator
+        | Instruction::CmpImmediateWithAccumulator
+        | Instruction::AndImmediateToAccumulator
+        | Instruction::TestImmediateDataAndAccumulator
+        | Instruction::OrImmediateToAccumulator
+        | Instruction::XorImmediateToAccumulator => match operands {
+            [Operand::Register(accumulator), Operand::Immediate(data)] => {
+                let word_byte_field = match which_register_field(accumulator) {
+                    Some((0b000, word_byte_field)) => word_byte_field,
+                    _ => return Err(invalid),
+                };
+
+                bytes.push(opcode | word_byte_field);
+                encode_immediate(&mut bytes, instruction, &word_byte_field, data)?;
+            }
+            _ => return Err(invalid),
+        },
+        Instruction::PushRegisterMemory
+        | Instruction::PopRegisterMemory
+        | Instruction::IncRegisterMemory
+        | Instruction::DecRegisterMemory
+        | Instruction::NegChangeSign
+        | Instruction::MultiplyUnsigned
+        | Instruction::IntegerMultiplySigned
+        | Instruction::DivideUnsigned
+        | Instruction::IntegerDivideSigned
+        | Instruction::NotInvert => match operands {
+            [rm] => {
+                let word_byte_field = which_word_byte_field(instruction, &decoded.width, operands)?;
+
+                bytes.push(opcode | word_byte_field);
+                encode_register_memory(
+                    &mut bytes,
+                    instruction,
+                    &group_field.unwrap_or_default(),
+                    rm,
+                    &decoded.encoding.displacement,
+                )?;
+            }
+            _ => return Err(invalid),
+        },
+        Instruction::PushRegister
+        | Instruction::PopRegister
+        | Instruction::IncRegister
+        | Instruction::DecRegister => match operands {
+            [Operand::Register(reg)] => match which_register_field(reg) {
+                Some((register_field, 0b1)) => bytes.push(opcode | register_field),
+                _ => return Err(invalid),
+            },
+            _ => return Err(invalid),
+        },
+        Instruction::XchgRegisterWithAccumulator => match operands {
+            [Operand::Register(EffectiveAddressCalculation::AX), Operand::Register(reg)]
+            | [Operand::Register(reg), Operand::Register(EffectiveAddressCalculation::AX)] => {
+                match which_register_field(reg) {
+                    Some((register_field, 0b1)) => bytes.push(opcode | register_field),
+                    _ => return Err(invalid),
+                }
+            }
+            _ => return Err(invalid),
+        },
+        Instruction::PushSegmentRegister | Instruction::PopSegmentRegister => match operands {
+            [Operand::SegmentRegister(sr)] => {
+                bytes.push(opcode | (which_segment_register_field(sr) << 3));
+            }
+            _ => return Err(invalid),
+        },
+        Instruction::InFixedPort
+        | Instruction::OutFixedPort
+        | Instruction::InVariablePort
+        | Instruction::OutVariablePort => {
+            let (accumulator, port) = match (instruction, operands) {
+                (
+                    Instruction::InFixedPort | Instruction::InVariablePort,
+                    [Operand::Register(accumulator), port],
+                )
+                | (
+                    Instruction::OutFixedPort | Instruction::OutVariablePort,
+                    [port, Operand::Register(accumulator)],
+                ) => (accumulator, port),
+                _ => return Err(invalid),
+            };
+
+            let word_byte_field = match which_register_field(accumulator) {
+                Some((0b000, word_byte_field)) => word_byte_field,
+                _ => return Err(invalid),
+            };
+
+            bytes.push(opcode | word_byte_field);
+
+            match (instruction, port) {
+                (
+                    Instruction::InFixedPort | Instruction::OutFixedPort,
+                    Operand::Immediate(port),
+                ) => encode_unsigned_byte(&mut bytes, instruction, port)?,
+                (
+                    Instruction::InVariablePort | Instruction::OutVariablePort,
+                    Operand::Register(EffectiveAddressCalculation::DX),
+                ) => {}
+                _ => return Err(invalid),
+            }
+        }
+        Instruction::ShiftLogicalLeft
+        | Instruction::ShiftLogicalRight
+        | Instruction::ShiftArithmeticRight
+        | Instruction::RotateLeft
+        | Instruction::RotateRight
+        | Instruction::RotateThroughCarryLeft
+        | Instruction::RotateThroughCarryRight => {
+            let (rm, variable_field) = match operands {
+                [rm, Operand::Immediate(1)] => (rm, 0b0),
+                [rm, Operand::Register(EffectiveAddressCalculation::CL)] => (rm, 0b1),
+                _ => return Err(invalid),
+            };
+            let word_byte_field =
+                which_word_byte_field(instruction, &decoded.width, &operands[..1])?;
+
+            bytes.push(opcode | (variable_field << 1) | word_byte_field);
+            encode_register_memory(
+                &mut bytes,
+                instruction,
+                &group_field.unwrap_or_default(),
+                rm,
+                &decoded.encoding.displacement,
+            )?;
+        }
+        Instruction::AsciiAdjustForMultiply | Instruction::AsciiAdjustForDivide => {
+            bytes.push(opcode);
+
+            match operands {
+                [Operand::Immediate(base)] => encode_unsigned_byte(&mut bytes, instruction, base)?,
+                [] => bytes.push(10),
+                _ => return Err(invalid),
+            }
+        }
+        Instruction::MoveByteOrWord
+        | Instruction::CompareByteOrWord
+        | Instruction::ScanByteOrWord
+        | Instruction::LoadByteOrWordToAlAx
+        | Instruction::StoreByteOrWordFromAlAx => {
+            let word_byte_field = which_word_byte_field(instruction, &decoded.width, operands)?;
+
+            bytes.push(opcode | word_byte_field);
+        }
+        Instruction::CallDirectWithinSegment | Instruction::JmpDirectWithinSegment => {
+            match operands {
+                [Operand::Relative(displacement)] => {
+                    bytes.push(opcode);
+                    bytes.extend(displacement.to_le_bytes());
+                }
+                _ => return Err(invalid),
+            }
+        }
+        Instruction::CallDirectIntersegment | Instruction::JmpDirectIntersegment => {
+            match operands {
+                [Operand::Intersegment { segment, offset }] => {
+                    bytes.push(opcode);
+                    bytes.extend(offset.to_le_bytes());
+                    bytes.extend(segment.to_le_bytes());
+                }
+                _ => return Err(invalid),
+            }
+        }
+        Instruction::CallIndirectWithinSegment
+        | Instruction::CallIndirectIntersegment
+        | Instruction::JmpIndirectWithinSegment
+        | Instruction::JmpIndirectIntersegment => {
+            let far = matches!(
+                instruction,
+                Instruction::CallIndirectIntersegment | Instruction::JmpIndirectIntersegment
+            );
+
+            match operands {
+                [Operand::Register(_)] if far => return Err(invalid),
+                [rm] => {
+                    bytes.push(opcode);
+                    encode_register_memory(
+                        &mut bytes,
+                        instruction,
+                        &group_field.unwrap_or_default(),
+                        rm,
+                        &decoded.encoding.displacement,
+                    )?;
+                }
+                _ => return Err(invalid),
+            }
+        }
+        Instruction::RetWithinSegmentAddingImmediateToSp
+        | Instruction::RetIntersegmentAddingImmediateToSp => match operands {
+            [Operand::Immediate(data)] => {
+                bytes.push(opcode);
+                bytes.extend(data.to_le_bytes());
+            }
+            _ => return Err(invalid),
+        },
+        Instruction::InterruptTypeSpecified => match operands {
+            [Operand::Immediate(data)] => {
+                bytes.push(opcode);
+                encode_unsigned_byte(&mut bytes, instruction, data)?;
+            }
+            _ => return Err(invalid),
+        },
+        Instruction::EscapeToExternalDevice => match operands {
+            [Operand::Immediate(external_opcode @ 0..=0b111111), rm] => {
+                // The external opcode is split across the low bits of both bytes.
+                let external_opcode = *external_opcode as u8;
+
+                bytes.push(opcode | (external_opcode >> 3));
+                encode_register_memory(
+                    &mut bytes,
+                    instruction,
+                    &(external_opcode & 0b111),
+                    rm,
+                    &decoded.encoding.displacement,
+                )?;
+            }
+            [Operand::Immediate(data), _] => {
+                return Err(EncodeError::ImmediateOutOfRange {
+                    instruction: *instruction,
+                    value: *data,
+                })
+            }
+            _ => return Err(invalid),
+        },
+        Instruction::JmpDirectWithinSegmentShort
+        | Instruction::JumpOnEqual
+        | Instruction::JumpOnLess
+        | Instruction::JumpOnLessOrEqual
+        | Instruction::JumpOnBelow
+        | Instruction::JumpOnBelowOrEqual
+        | Instruction::JumpOnParity
+        | Instruction::JumpOnOverflow
+        | Instruction::JumpOnSign
+        | Instruction::JumpOnNotEqual
+        | Instruction::JumpOnNotLess
+        | Instruction::JumpOnGreater
+        | Instruction::JumpOnNotBelow
+        | Instruction::JumpOnAbove
+        | Instruction::JumpOnNotPar
+        | Instruction::JumpOnNotOverflow
+        | Instruction::JumpOnNotSign
+        | Instruction::JumpOnCxZero
+        | Instruction::LoopCxTimes
+        | Instruction::LoopWhileZero
+        | Instruction::LoopWhileNotZero => match operands {
+            [Operand::Relative(displacement)] => {
+                bytes.push(opcode);
+                encode_short_relative(&mut bytes, instruction, displacement)?;
+            }
+            _ => return Err(invalid),
+        },
+        _ => match operands {
+            [] => bytes.push(opcode),
+            _ => return Err(invalid),
+        },
+    }
+
+    Ok(bytes)
+}
@@ -0,0 +1,60 @@
+use crate::decoder::constants::Instruction;
+use std::{error::Error, fmt};
+
+// Encode Errors
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EncodeError {
+    // `instruction` is a prefix or a placeholder, it has no bytes of its own.
+    NoEncoding {
+        instruction: Instruction,
+    },
+    // The operands don't match any encoding of `instruction`.
+    InvalidOperands {
+        instruction: Instruction,
+    },
+    // No width was given and no register operand implies one.
+    UnknownWidth {
+        instruction: Instruction,
+    },
+    // An immediate doesn't fit the field it is encoded into.
+    ImmediateOutOfRange {
+        instruction: Instruction,
+        value: i16,
+    },
+    // A short jump or loop can't reach its target.
+    RelativeOutOfRange {
+        instruction: Instruction,
+        displacement: i16,
+    },
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EncodeError::NoEncoding { instruction } => {
+                write!(f, "{:?} has no encoding of its own", instruction)
+            }
+            EncodeError::InvalidOperands { instruction } => {
+                write!(f, "invalid operands for {:?}", instruction)
+            }
+            EncodeError::UnknownWidth { instruction } => {
+                write!(f, "unknown operand width for {:?}", instruction)
+            }
+            EncodeError::ImmediateOutOfRange { instruction, value } => {
+                write!(f, "immediate {} out of range for {:?}", value, instruction)
+            }
+            EncodeError::RelativeOutOfRange {
+                instruction,
+                displacement,
+            } => {
+                write!(
+                    f,
+                    "displacement {} out of range for {:?}",
+                    displacement, instruction
+                )
+            }
+        }
+    }
+}
+
+impl Error for EncodeError {}
@@ -0,0 +1,431 @@
+use crate::decoder::constants::*;
+use crate::decoder::decoded_instruction::{MemoryAddress, Operand, Prefixes, Width};
+use crate::encoder::error::EncodeError;
+
+// The first opcode byte of each form with its d, s, v and w bits cleared,
+// and the reg field for the forms that share an opcode with others.
+pub(crate) fn which_opcode(instruction: &Instruction) -> Option<(u8, Option<u8>)> {
+    let opcode = match instruction {
+        // MOV Instructions
+        Instruction::MovRegisterMemoryToFromRegister => (0b10001000, None),
+        Instruction::MovImmediateToRegisterMemory => (0b11000110, Some(0b000)),
+        Instruction::MovImmediateToRegister => (0b10110000, None),
+        Instruction::MovMemoryToAccumulator => (0b10100000, None),
+        Instruction::MovAccumulatorToMemory => (0b10100010, None),
+        Instruction::MovRegisterMemoryToSegmentRegister => (0b10001110, None),
+        Instruction::MovSegmentRegisterToRegisterMemory => (0b10001100, None),
+
+        // PUSH Instructions
+        Instruction::PushRegisterMemory => (0b11111111, Some(0b110)),
+        Instruction::PushRegister => (0b01010000, None),
+        Instruction::PushSegmentRegister => (0b00000110, None),
+
+        // POP Instructions
+        Instruction::PopRegisterMemory => (0b10001111, Some(0b000)),
+        Instruction::PopRegister => (0b01011000, None),
+        Instruction::PopSegmentRegister => (0b00000111, None),
+
+        // XCHG Instructions
+        Instruction::XchgRegisterMemoryWithRegister => (0b10000110, None),
+        Instruction::XchgRegisterWithAccumulator => (0b10010000, None),
+
+        // IN Instructions
+        Instruction::InFixedPort => (0b11100100, None),
+        Instruction::InVariablePort => (0b11101100, None),
+
+        // OUT Instructions
+        Instruction::OutFixedPort => (0b11100110, None),
+        Instruction::OutVariablePort => (0b11101110, None),
+
+        // Other Data Transfer Instructions
+        Instruction::TranslateByteToAl => (0b11010111, None),
+        Instruction::LoadEffectiveAddressToRegister => (0b10001101, None),
+        Instruction::LoadPointerToDs => (0b11000101, None),
+        Instruction::LoadPointerToEs => (0b11000100, None),
+        Instruction::LoadAhWithFlags => (0b10011111, None),
+        Instruction::StoreAhIntoFlags => (0b10011110, None),
+        Instruction::PushFlags => (0b10011100, None),
+        Instruction::PopFlags => (0b10011101, None),
+
+        // ADD Instructions
+        Instruction::AddRegisterMemoryWithRegisterToEither => (0b00000000, None),
+        Instruction::AddImmediateToRegisterMemory => (0b10000000, Some(0b000)),
+        Instruction::AddImmediateToAccumulator => (0b00000100, None),
+
+        // ADC Instructions
+        Instruction::AdcRegisterMemoryWithRegisterToEither => (0b00010000, None),
+        Instruction::AdcImmediateToRegisterMemory => (0b10000000, Some(0b010)),
+        Instruction::AdcImmediateToAccumulator => (0b00010100, None),
+
+        // INC Instructions
+        Instruction::IncRegisterMemory => (0b11111110, Some(0b000)),
+        Instruction::IncRegister => (0b01000000, None),
+
+        // SUB Instructions
+        Instruction::SubRegisterMemoryAndRegisterToEither => (0b00101000, None),
+        Instruction::SubImmediateFromRegisterMemory => (0b10000000, Some(0b101)),
+        Instruction::SubImmediateFromAccumulator => (0b00101100, None),
+
+        // SBB Instructions
+        Instruction::SbbRegisterMemoryAndRegisterToEither => (0b00011000, None),
+        Instruction::SbbImmediateFromRegisterMemory => (0b10000000, Some(0b011)),
+        Instruction::SbbImmediateFromAccumulator => (0b00011100, None),
+
+        // DEC Instructions
+        Instruction::DecRegisterMemory => (0b11111110, Some(0b001)),
+        Instruction::DecRegister => (0b01001000, None),
+
+        // NEG Instructions
+        Instruction::NegChangeSign => (0b11110110, Some(0b011)),
+
+        // CMP Instructions
+        Instruction::CmpRegisterMemoryAndRegister => (0b00111000, None),
+        Instruction::CmpImmediateWithRegisterMemory => (0b10000000, Some(0b111)),
+        Instruction::CmpImmediateWithAccumulator => (0b00111100, None),
+
+        // MUL, IMUL, DIV, IDIV Instructions
+        Instruction::MultiplyUnsigned => (0b11110110, Some(0b100)),
+        Instruction::IntegerMultiplySigned => (0b11110110, Some(0b101)),
+        Instruction::DivideUnsigned => (0b11110110, Some(0b110)),
+        Instruction::IntegerDivideSigned => (0b11110110, Some(0b111)),
+
+        // Adjust and Convert Instructions
+        Instruction::AsciiAdjustForAdd => (0b00110111, None),
+        Instruction::DecimalAdjustForAdd => (0b00100111, None),
+        Instruction::AsciiAdjustForSubtract => (0b00111111, None),
+        Instruction::DecimalAdjustForSubtract => (0b00101111, None),
+        Instruction::AsciiAdjustForMultiply => (0b11010100, None),
+        Instruction::AsciiAdjustForDivide => (0b11010101, None),
+        Instruction::ConvertByteToWord => (0b10011000, None),
+        Instruction::ConvertWordToDoubleWord => (0b10011001, None),
+
+        // NOT Instructions
+        Instruction::NotInvert => (0b11110110, Some(0b010)),
+
+        // Shift and Rotate Instructions
+        Instruction::ShiftLogicalLeft => (0b11010000, Some(0b100)),
+        Instruction::ShiftLogicalRight => (0b11010000, Some(0b101)),
+        Instruction::ShiftArithmeticRight => (0b11010000, Some(0b111)),
+        Instruction::RotateLeft => (0b11010000, Some(0b000)),
+        Instruction::RotateRight => (0b11010000, Some(0b001)),
+        Instruction::RotateThroughCarryLeft => (0b11010000, Some(0b010)),
+        Instruction::RotateThroughCarryRight => (0b11010000, Some(0b011)),
+
+        // AND Instructions
+        Instruction::AndRegisterMemoryWithRegisterToEither => (0b00100000, None),
+        Instruction::AndImmediateToRegisterMemory => (0b10000000, Some(0b100)),
+        Instruction::AndImmediateToAccumulator => (0b00100100, None),
+
+        // TEST Instructions
+        Instruction::TestRegisterMemoryAndRegister => (0b10000100, None),
+        Instruction::TestImmediateDataAndRegisterMemory => (0b11110110, Some(0b000)),
+        Instruction::TestImmediateDataAndAccumulator => (0b10101000, None),
+
+        // OR Instructions
+        Instruction::OrRegisterMemoryAndRegisterToEither => (0b00001000, None),
+        Instruction::OrImmediateToRegisterMemory => (0b10000000, Some(0b001)),
+        Instruction::OrImmediateToAccumulator => (0b00001100, None),
+
+        // XOR Instructions
+        Instruction::XorRegisterMemoryAndRegisterToEither => (0b00110000, None),
+        Instruction::XorImmediateToRegisterMemory => (0b10000000, Some(0b110)),
+        Instruction::XorImmediateToAccumulator => (0b00110100, None),
+
+        // String Manipulation Instructions
+        Instruction::MoveByteOrWord => (0b10100100, None),
+        Instruction::CompareByteOrWord => (0b10100110, None),
+        Instruction::ScanByteOrWord => (0b10101110, None),
+        Instruction::LoadByteOrWordToAlAx => (0b10101100, None),
+        Instruction::StoreByteOrWordFromAlAx => (0b10101010, None),
+
+        // CALL Instructions
+        Instruction::CallDirectWithinSegment => (0b11101000, None),
+        Instruction::CallIndirectWithinSegment => (0b11111111, Some(0b010)),
+        Instruction::CallDirectIntersegment => (0b10011010, None),
+        Instruction::CallIndirectIntersegment => (0b11111111, Some(0b011)),
+
+        // JMP Instructions
+        Instruction::JmpDirectWithinSegment => (0b11101001, None),
+        Instruction::JmpDirectWithinSegmentShort => (0b11101011, None),
+        Instruction::JmpIndirectWithinSegment => (0b11111111, Some(0b100)),
+        Instruction::JmpDirectIntersegment => (0b11101010, None),
+        Instruction::JmpIndirectIntersegment => (0b11111111, Some(0b101)),
+
+        // RET Instructions
+        Instruction::RetWithinSegment => (0b11000011, None),
+        Instruction::RetWithinSegmentAddingImmediateToSp => (0b11000010, None),
+        Instruction::RetIntersegment => (0b11001011, None),
+        Instruction::RetIntersegmentAddingImmediateToSp => (0b11001010, None),
+
+        // Interrupt Instructions
+        Instruction::InterruptTypeSpecified => (0b11001101, None),
+        Instruction::InterruptType3 => (0b11001100, None),
+        Instruction::InterruptOnOverflow => (0b11001110, None),
+        Instruction::InterruptReturn => (0b11001111, None),
+
+        // Processor Control Instructions
+        Instruction::ClearCarry => (0b11111000, None),
+        Instruction::ComplementCarry => (0b11110101, None),
+        Instruction::SetCarry => (0b11111001, None),
+        Instruction::ClearDirection => (0b11111100, None),
+        Instruction::SetDirection => (0b11111101, None),
+        Instruction::ClearInterrupt => (0b11111010, None),
+        Instruction::SetInterrupt => (0b11111011, None),
+        Instruction::Halt => (0b11110100, None),
+        Instruction::WaitForTest => (0b10011011, None),
+        Instruction::EscapeToExternalDevice => (0b11011000, None),
+
+        // Jump Instructions
+        Instruction::JumpOnEqual => (0b01110100, None),
+        Instruction::JumpOnLess => (0b01111100, None),
+        Instruction::JumpOnLessOrEqual => (0b01111110, None),
+        Instruction::JumpOnBelow => (0b01110010, None),
+        Instruction::JumpOnBelowOrEqual => (0b01110110, None),
+        Instruction::JumpOnParity => (0b01111010, None),
+        Instruction::JumpOnOverflow => (0b01110000, None),
+        Instruction::JumpOnSign => (0b01111000, None),
+        Instruction::JumpOnNotEqual => (0b01110101, None),
+        Instruction::JumpOnNotLess => (0b01111101, None),
+        Instruction::JumpOnGreater => (0b01111111, None),
+        Instruction::JumpOnNotBelow => (0b01110011, None),
+        Instruction::JumpOnAbove => (0b01110111, None),
+        Instruction::JumpOnNotPar => (0b01111011, None),
+        Instruction::JumpOnNotOverflow => (0b01110001, None),
+        Instruction::JumpOnNotSign => (0b01111001, None),
+        Instruction::JumpOnCxZero => (0b11100011, None),
+
+        // Loop Instructions
+        Instruction::LoopCxTimes => (0b11100010, None),
+        Instruction::LoopWhileZero => (0b11100001, None),
+        Instruction::LoopWhileNotZero => (0b11100000, None),
+
+        // Prefixes are carried by the instruction that follows them.
+        Instruction::Repeat
+        | Instruction::BusLockPrefix
+        | Instruction::SegmentOverridePrefix
+        | Instruction::Invalid => return None,
+    };
+
+    Some(opcode)
+}
+
+// Appends the prefix bytes in the order NASM writes them: repeat, lock and
+// then the segment override.
+pub(crate) fn encode_prefixes(
+    bytes: &mut Vec<u8>,
+    prefixes: &Prefixes,
+    segment_override: &Option<SegmentRegister>,
+) {
+    match prefixes.repeat {
+        Some(Op::Repne) => bytes.push(0b11110010),
+        Some(_) => bytes.push(0b11110011),
+        None => {}
+    }
+
+    if prefixes.lock {
+        bytes.push(0b11110000);
+    }
+
+    if let Some(segment_register) = segment_override {
+        bytes.push(0b00100110 | (which_segment_register_field(segment_register) << 3));
+    }
+}
+
+// The reg field and w bit that select `register`, or None for the memory
+// bases.
+pub(crate) fn which_register_field(register: &EffectiveAddressCalculation) -> Option<(u8, u8)> {
+    let field = match register {
+        EffectiveAddressCalculation::AL => (0b000, 0b0),
+        EffectiveAddressCalculation::CL => (0b001, 0b0),
+        EffectiveAddressCalculation::DL => (0b010, 0b0),
+        EffectiveAddressCalculation::BL => (0b011, 0b0),
+        EffectiveAddressCalculation::AH => (0b100, 0b0),
+        EffectiveAddressCalculation::CH => (0b101, 0b0),
+        EffectiveAddressCalculation::DH => (0b110, 0b0),
+        EffectiveAddressCalculation::BH => (0b111, 0b0),
+        EffectiveAddressCalculation::AX => (0b000, 0b1),
+        EffectiveAddressCalculation::CX => (0b001, 0b1),
+        EffectiveAddressCalculation::DX => (0b010, 0b1),
+        EffectiveAddressCalculation::BX => (0b011, 0b1),
+        EffectiveAddressCalculation::SP => (0b100, 0b1),
+        EffectiveAddressCalculation::BP => (0b101, 0b1),
+        EffectiveAddressCalculation::SI => (0b110, 0b1),
+        EffectiveAddressCalculation::DI => (0b111, 0b1),
+        _ => return None,
+    };
+
+    Some(field)
+}
+
+// The r/m field that selects the memory base `base`, or None for registers.
+pub(crate) fn which_rm_field(base: &EffectiveAddressCalculation) -> Option<u8> {
+    let field = match base {
+        EffectiveAddressCalculation::BxSi => 0b000,
+        EffectiveAddressCalculation::BxDi => 0b001,
+        EffectiveAddressCalculation::BpSi => 0b010,
+        EffectiveAddressCalculation::BpDi => 0b011,
+        EffectiveAddressCalculation::Si => 0b100,
+        EffectiveAddressCalculation::Di => 0b101,
+        EffectiveAddressCalculation::Bp => 0b110,
+        EffectiveAddressCalculation::Bx => 0b111,
+        _ => return None,
+    };
+
+    Some(field)
+}
+
+pub(crate) fn which_segment_register_field(segment_register: &SegmentRegister) -> u8 {
+    match segment_register {
+        SegmentRegister::ES => 0b00,
+        SegmentRegister::CS => 0b01,
+        SegmentRegister::SS => 0b10,
+        SegmentRegister::DS => 0b11,
+    }
+}
+
+// The w bit of an instruction: its width if it has one, otherwise the width
+// of the first register among `operands`.
+pub(crate) fn which_word_byte_field(
+    instruction: &Instruction,
+    width: &Option<Width>,
+    operands: &[Operand],
+) -> Result<u8, EncodeError> {
+    match width {
+        Some(Width::Byte) => Ok(0b0),
+        Some(Width::Word) => Ok(0b1),
+        None => operands
+            .iter()
+            .find_map(|operand| match operand {
+                Operand::Register(register) => which_register_field(register),
+                _ => None,
+            })
+            .map(|(_, word_byte_field)| word_byte_field)
+            .ok_or(EncodeError::UnknownWidth {
+                instruction: *instruction,
+            }),
+    }
+}
+
+pub(crate) fn register_field(
+    instruction: &Instruction,
+    register: &EffectiveAddressCalculation,
+) -> Result<u8, EncodeError> {
+    which_register_field(register)
+        .map(|(field, _)| field)
+        .ok_or(EncodeError::InvalidOperands {
+            instruction: *instruction,
+        })
+}
+
+// Appends the mod/reg/rm byte and any displacement for `operand`, picking
+// the shortest displacement that holds it unless `displacement_width` asks
+// for a wider one.
+pub(crate) fn encode_register_memory(
+    bytes: &mut Vec<u8>,
+    instruction: &Instruction,
+    register_field: &u8,
+    operand: &Operand,
+    displacement_width: &Option<Width>,
+) -> Result<(), EncodeError> {
+    let invalid = EncodeError::InvalidOperands {
+        instruction: *instruction,
+    };
+
+    match operand {
+        Operand::Register(register) => {
+            let (rm_field, _) = which_register_field(register).ok_or(invalid)?;
+            bytes.push((0b11 << 6) | (register_field << 3) | rm_field);
+        }
+        Operand::Memory(MemoryAddress {
+            base: None,
+            displacement,
+            ..
+        }) => {
+            // Direct address: mod = 00 with r/m = 110.
+            bytes.push((register_field << 3) | 0b110);
+            bytes.extend(displacement.to_le_bytes());
+        }
+        Operand::Memory(MemoryAddress {
+            base: Some(base),
+            displacement,
+            ..
+        }) => {
+            let rm_field = which_rm_field(base).ok_or(invalid)?;
+
+            let byte_displacement = i8::try_from(*displacement);
+
+            let mode_field = match displacement_width {
+                Some(Width::Word) => 0b10,
+                Some(Width::Byte) => 0b01,
+                // [bp] has no mod = 00 form, that slot is the direct address.
+                None if *displacement == 0 && *base != EffectiveAddressCalculation::Bp => 0b00,
+                None if byte_displacement.is_ok() => 0b01,
+                None => 0b10,
+            };
+
+            bytes.push((mode_field << 6) | (register_field << 3) | rm_field);
+
+            match mode_field {
+                0b01 => bytes.extend(byte_displacement.map_err(|_| invalid)?.to_le_bytes()),
+                0b10 => bytes.extend(displacement.to_le_bytes()),
+                _ => {}
+            }
+        }
+        _ => return Err(invalid),
+    }
+
+    Ok(())
+}
+
+// Appends `data` as one byte or two. A byte takes values from -128 to 255 so
+// both signed and unsigned spellings fit.
+pub(crate) fn encode_immediate(
+    bytes: &mut Vec<u8>,
+    instruction: &Instruction,
+    word_byte_field: &u8,
+    data: &i16,
+) -> Result<(), EncodeError> {
+    if *word_byte_field == 0b1 {
+        bytes.extend(data.to_le_bytes());
+    } else if (-128..=255).contains(data) {
+        bytes.push(*data as u8);
+    } else {
+        return Err(EncodeError::ImmediateOutOfRange {
+            instruction: *instruction,
+            value: *data,
+        });
+    }
+
+    Ok(())
+}
+
+// Appends an unsigned byte field such as a port, interrupt type or base.
+pub(crate) fn encode_unsigned_byte(
+    bytes: &mut Vec<u8>,
+    instruction: &Instruction,
+    data: &i16,
+) -> Result<(), EncodeError> {
+    let data = u8::try_from(*data).map_err(|_| EncodeError::ImmediateOutOfRange {
+        instruction: *instruction,
+        value: *data,
+    })?;
+    bytes.push(data);
+
+    Ok(())
+}
+
+// Appends a displacement that must fit in a signed byte.
+pub(crate) fn encode_short_relative(
+    bytes: &mut Vec<u8>,
+    instruction: &Instruction,
+    displacement: &i16,
+) -> Result<(), EncodeError> {
+    let displacement =
+        i8::try_from(*displacement).map_err(|_| EncodeError::RelativeOutOfRange {
+            instruction: *instruction,
+            displacement: *displacement,
+        })?;
+    bytes.extend(displacement.to_le_bytes());
+
+    Ok(())
+}
@@ -0,0 +1,168 @@
+pub mod att;
+pub mod masm;
+pub mod nasm;
+
+use crate::decoder::constants::{Instruction, Op};
+use crate::decoder::decoded_instruction::{DecodedInstruction, Operand, Width};
+use crate::decoder::error::DecodeError;
+
+pub use att::Att;
+pub use masm::Masm;
+pub use nasm::Nasm;
+
+/// An assembler syntax a listing can be written in.
+pub trait Syntax {
+    /// The directive a listing opens with.
+    fn header(&self) -> &'static str;
+
+    /// Formats one decoded instruction, writing its relative operand as
+    /// `label` when one is given.
+    fn format_instruction(&self, decoded: &DecodedInstruction, label: Option<&str>) -> String;
+
+    /// Formats one byte as a data line.
+    fn format_byte(&self, byte: u8) -> String;
+
+    /// The token a comment starts with.
+    fn comment(&self) -> &'static str {
+        ";"
+    }
+
+    /// Formats the byte at which `error` stopped decoding as a data line, so
+    /// the listing still reassembles to the original bytes.
+    fn format_undecodable(&self, contents: &[u8], error: &DecodeError) -> String {
+        format!(
+            "{} {} {}",
+            self.format_byte(contents[error.offset()]),
+            self.comment(),
+            error
+        )
+    }
+}
+
+/// Looks a syntax up by its command line name: `nasm`, `masm` or `att`.
+pub fn which_syntax(name: &str) -> Option<&'static dyn Syntax> {
+    match name {
+        "nasm" => Some(&Nasm),
+        "masm" | "tasm" => Some(&Masm),
+        "att" | "gas" => Some(&Att),
+        _ => None,
+    }
+}
+
+/// Formats one decoded instruction as a line of NASM source.
+pub fn format_instruction(decoded: &DecodedInstruction) -> String {
+    Nasm.format_instruction(decoded, None)
+}
+
+/// Formats one decoded instruction, writing its relative operand as `label`.
+pub fn format_instruction_with_label(decoded: &DecodedInstruction, label: &str) -> String {
+    Nasm.format_instruction(decoded, Some(label))
+}
+
+/// Formats the byte at which `error` stopped decoding as a `db` line.
+pub fn format_undecodable(contents: &[u8], error: &DecodeError) -> String {
+    Nasm.format_undecodable(contents, error)
+}
+
+// `here` is the syntax's name for the start of the current instruction.
+fn which_relative(decoded: &DecodedInstruction, displacement: &i16, here: &str) -> String {
+    // The 8086 displacement counts from the next instruction.
+    let offset = *displacement as i32 + decoded.length as i32;
+
+    if offset.is_negative() {
+        format!("{}-{}", here, -offset)
+    } else {
+        format!("{}+{}", here, offset)
+    }
+}
+
+fn format_mnemonic(decoded: &DecodedInstruction) -> String {
+    match (decoded.op, decoded.width) {
+        (Op::Invalid, _) => String::new(),
+        (Op::Movs | Op::Cmps | Op::Scas | Op::Lods | Op::Stos, Some(width)) => {
+            let suffix = &width.to_string()[..1];
+            format!("{}{}", decoded.op, suffix)
+        }
+        (op, _) => op.to_string(),
+    }
+}
+
+// The prefixes written ahead of the mnemonic: lock, repeat, and a segment
+// override when no memory operand already shows it.
+fn format_prefixes(decoded: &DecodedInstruction) -> Vec<String> {
+    let mut parts: Vec<String> = Vec::new();
+
+    if decoded.prefixes.lock {
+        parts.push(Op::Lock.to_string());
+    }
+
+    if let Some(repeat) = decoded.prefixes.repeat {
+        parts.push(repeat.to_string());
+    }
+
+    if let Some(segment_register) = decoded.prefixes.segment {
+        let consumed = decoded
+            .operands
+            .iter()
+            .any(|operand| matches!(operand, Operand::Memory(_)));
+
+        if !consumed {
+            parts.push(segment_register.to_string());
+        }
+    }
+
+    parts
+}
+
+// The width of a memory operand that no register operand implies. Syntaxes
+// that size the memory operand rather than the immediate (MASM's `ptr`, the
+// AT&T suffix) only need it there.
+fn which_memory_width(decoded: &DecodedInstruction) -> Option<Width> {
+    let sized = match decoded.instruction {
+        // Calls and jumps are sized by their distance instead.
+        Instruction::CallIndirectWithinSegment
+        | Instruction::CallIndirectIntersegment
+        | Instruction::JmpIndirectWithinSegment
+        | Instruction::JmpIndirectIntersegment => return None,
+        // The count in CL says nothing about the width of what is shifted.
+        Instruction::ShiftLogicalLeft
+        | Instruction::ShiftLogicalRight
+        | Instruction::ShiftArithmeticRight
+        | Instruction::RotateLeft
+        | Instruction::RotateRight
+        | Instruction::RotateThroughCarryLeft
+        | Instruction::RotateThroughCarryRight => &decoded.operands[..1],
+        _ => &decoded.operands[..],
+    };
+
+    let memory = sized
+        .iter()
+        .any(|operand| matches!(operand, Operand::Memory(_)));
+    let register = sized
+        .iter()
+        .any(|operand| matches!(operand, Operand::Register(_) | Operand::SegmentRegister(_)));
+
+    if memory && !register {
+        decoded.width
+    } else {
+        None
+    }
+}
+
+// The base of AAM and AAD is implied when it is the usual 10.
+fn is_default_base(decoded: &DecodedInstruction) -> bool {
+    matches!(
+        decoded.instruction,
+        Instruction::AsciiAdjustForMultiply | Instruction::AsciiAdjustForDivide
+    ) && decoded.operands == [Operand::Immediate(10)]
+}
+
+fn join_line(parts: Vec<String>, operands: Vec<String>) -> String {
+    let line = parts.join(" ");
+
+    if operands.is_empty() {
+        line
+    } else {
+        format!("{} {}", line, operands.join(", "))
+    }
+}
@@ -0,0 +1,126 @@
+use crate::decoder::constants::{EffectiveAddressCalculation, Instruction, Op};
+use crate::decoder::decoded_instruction::{DecodedInstruction, MemoryAddress, Operand, Width};
+use crate::formatter::*;
+
+/// GNU AT&T syntax, as objdump prints it: `movw $12, 4(%bx,%si)`. Operands
+/// run source first.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Att;
+
+fn format_memory(address: &MemoryAddress) -> String {
+    let displacement = address.displacement as i32;
+
+    let address_calculation = match address.base {
+        Some(base) => {
+            let registers: Vec<String> = base
+                .to_string()
+                .split(" + ")
+                .map(|register| format!("%{}", register))
+                .collect();
+            let registers = format!("({})", registers.join(","));
+
+            if displacement == 0 {
+                registers
+            } else {
+                format!("{}{}", displacement, registers)
+            }
+        }
+        // A bare number is a memory operand in AT&T syntax.
+        None => (address.displacement as u16).to_string(),
+    };
+
+    match address.segment {
+        Some(segment_register) => format!("%{}:{}", segment_register, address_calculation),
+        None => address_calculation,
+    }
+}
+
+fn format_operand(decoded: &DecodedInstruction, operand: &Operand, label: Option<&str>) -> String {
+    match operand {
+        // The port in DX is written as if it were memory.
+        Operand::Register(EffectiveAddressCalculation::DX)
+            if matches!(
+                decoded.instruction,
+                Instruction::InVariablePort | Instruction::OutVariablePort
+            ) =>
+        {
+            "(%dx)".to_string()
+        }
+        Operand::Register(register) => format!("%{}", register),
+        Operand::SegmentRegister(segment_register) => format!("%{}", segment_register),
+        Operand::Memory(address) => format_memory(address),
+        Operand::Immediate(data) => format!("${}", data),
+        Operand::Relative(displacement) => match label {
+            Some(label) => label.to_string(),
+            // GAS's `.` is the start of the current instruction.
+            None => which_relative(decoded, displacement, "."),
+        },
+        Operand::Intersegment { segment, offset } => format!("${}, ${}", segment, offset),
+    }
+}
+
+fn format_att_mnemonic(decoded: &DecodedInstruction) -> String {
+    let mnemonic = match (decoded.instruction, decoded.op) {
+        (Instruction::CallDirectIntersegment | Instruction::CallIndirectIntersegment, _) => {
+            "lcall".to_string()
+        }
+        (Instruction::JmpDirectIntersegment | Instruction::JmpIndirectIntersegment, _) => {
+            "ljmp".to_string()
+        }
+        (_, Op::Retf) => "lret".to_string(),
+        (_, Op::Cbw) => "cbtw".to_string(),
+        (_, Op::Cwd) => "cwtd".to_string(),
+        _ => format_mnemonic(decoded),
+    };
+
+    // The suffix sizes an operation no register operand implies.
+    match which_memory_width(decoded) {
+        Some(Width::Byte) => format!("{}b", mnemonic),
+        Some(Width::Word) => format!("{}w", mnemonic),
+        None => mnemonic,
+    }
+}
+
+impl Syntax for Att {
+    fn header(&self) -> &'static str {
+        ".code16"
+    }
+
+    fn format_instruction(&self, decoded: &DecodedInstruction, label: Option<&str>) -> String {
+        let mut operands: Vec<String> = decoded
+            .operands
+            .iter()
+            .rev()
+            .map(|operand| format_operand(decoded, operand, label))
+            .collect();
+
+        match decoded.instruction {
+            // Indirect targets are marked with a `*`.
+            Instruction::CallIndirectWithinSegment
+            | Instruction::CallIndirectIntersegment
+            | Instruction::JmpIndirectWithinSegment
+            | Instruction::JmpIndirectIntersegment => {
+                operands[0] = format!("*{}", operands[0]);
+            }
+            _ if is_default_base(decoded) => operands.clear(),
+            _ => {}
+        }
+
+        let mut parts = format_prefixes(decoded);
+
+        let mnemonic = format_att_mnemonic(decoded);
+        if !mnemonic.is_empty() {
+            parts.push(mnemonic);
+        }
+
+        join_line(parts, operands)
+    }
+
+    fn format_byte(&self, byte: u8) -> String {
+        format!(".byte {:#04x}", byte)
+    }
+
+    fn comment(&self) -> &'static str {
+        "#"
+    }
+}
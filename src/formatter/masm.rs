@@ -0,0 +1,89 @@
+use crate::decoder::constants::Instruction;
+use crate::decoder::decoded_instruction::{DecodedInstruction, MemoryAddress, Operand};
+use crate::formatter::*;
+
+/// MASM and TASM syntax: `mov word ptr [bx+si+4], 12`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Masm;
+
+fn format_memory(address: &MemoryAddress) -> String {
+    let displacement = address.displacement as i32;
+    let base = address.base.map(|base| base.to_string().replace(' ', ""));
+
+    let address_calculation = match base {
+        Some(base) if displacement.is_negative() => format!("[{}-{}]", base, -displacement),
+        Some(base) if displacement.is_positive() => format!("[{}+{}]", base, displacement),
+        Some(base) => format!("[{}]", base),
+        None => format!("[{}]", address.displacement as u16),
+    };
+
+    // MASM reads a bare `[1234]` as the number 1234, so a direct address
+    // always carries its segment.
+    match (address.segment, address.base) {
+        (Some(segment_register), _) => format!("{}:{}", segment_register, address_calculation),
+        (None, None) => format!("ds:{}", address_calculation),
+        (None, Some(_)) => address_calculation,
+    }
+}
+
+fn format_operand(decoded: &DecodedInstruction, operand: &Operand, label: Option<&str>) -> String {
+    match operand {
+        Operand::Register(register) => register.to_string(),
+        Operand::SegmentRegister(segment_register) => segment_register.to_string(),
+        Operand::Memory(address) => match which_memory_width(decoded) {
+            Some(width) => format!("{} ptr {}", width, format_memory(address)),
+            None => format_memory(address),
+        },
+        Operand::Immediate(data) => data.to_string(),
+        Operand::Relative(displacement) => match label {
+            Some(label) => label.to_string(),
+            None => which_relative(decoded, displacement, "$"),
+        },
+        Operand::Intersegment { segment, offset } => format!("{}:{}", segment, offset),
+    }
+}
+
+impl Syntax for Masm {
+    fn header(&self) -> &'static str {
+        ".8086"
+    }
+
+    fn format_instruction(&self, decoded: &DecodedInstruction, label: Option<&str>) -> String {
+        let mut operands: Vec<String> = decoded
+            .operands
+            .iter()
+            .map(|operand| format_operand(decoded, operand, label))
+            .collect();
+
+        match decoded.instruction {
+            Instruction::CallIndirectIntersegment | Instruction::JmpIndirectIntersegment => {
+                operands[0] = format!("dword ptr {}", operands[0]);
+            }
+            Instruction::CallDirectIntersegment | Instruction::JmpDirectIntersegment => {
+                operands[0] = format!("far ptr {}", operands[0]);
+            }
+            Instruction::JmpDirectWithinSegment => {
+                operands[0] = format!("near ptr {}", operands[0]);
+            }
+            Instruction::JmpDirectWithinSegmentShort => {
+                operands[0] = format!("short {}", operands[0]);
+            }
+            _ if is_default_base(decoded) => operands.clear(),
+            _ => {}
+        }
+
+        let mut parts = format_prefixes(decoded);
+
+        let mnemonic = format_mnemonic(decoded);
+        if !mnemonic.is_empty() {
+            parts.push(mnemonic);
+        }
+
+        join_line(parts, operands)
+    }
+
+    // MASM hex needs a leading digit: `0ffh`.
+    fn format_byte(&self, byte: u8) -> String {
+        format!("db {:03x}h", byte)
+    }
+}
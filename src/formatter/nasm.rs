@@ -0,0 +1,206 @@
+use crate::assembler::assemble;
+use crate::decoder::constants::{EffectiveAddressCalculation, Instruction, Op};
+use crate::decoder::decoded_instruction::{DecodedInstruction, MemoryAddress, Operand, Width};
+use crate::encoder::encode;
+use crate::formatter::*;
+
+/// NASM syntax, the one the listings are written in: `mov word [bx + si + 4], 12`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Nasm;
+
+fn format_memory(address: &MemoryAddress) -> String {
+    let displacement = address.displacement as i32;
+
+    let address_calculation = match address.base {
+        Some(base) if displacement.is_negative() => format!("[{} - {}]", base, -displacement),
+        Some(base) if displacement.is_positive() => format!("[{} + {}]", base, displacement),
+        Some(base) => format!("[{}]", base),
+        // Direct addresses are a plain 16-bit offset.
+        None => format!("[{}]", address.displacement as u16),
+    };
+
+    match address.segment {
+        Some(segment_register) => format!("{}:{}", segment_register, address_calculation),
+        None => address_calculation,
+    }
+}
+
+// Traces write immediates unsigned at the width of the operation, all but
+// the sign-extended bytes of the immediate arithmetic forms.
+fn format_unsigned_immediate(decoded: &DecodedInstruction, data: &i16) -> String {
+    let sign_extended = matches!(
+        decoded.instruction,
+        Instruction::AddImmediateToRegisterMemory
+            | Instruction::AdcImmediateToRegisterMemory
+            | Instruction::SubImmediateFromRegisterMemory
+            | Instruction::SbbImmediateFromRegisterMemory
+            | Instruction::CmpImmediateWithRegisterMemory
+    ) && (-128..=127).contains(data);
+
+    match decoded.width {
+        _ if sign_extended => data.to_string(),
+        Some(Width::Byte) => (*data as u8).to_string(),
+        Some(Width::Word) => (*data as u16).to_string(),
+        None => data.to_string(),
+    }
+}
+
+fn format_operand(
+    decoded: &DecodedInstruction,
+    operand: &Operand,
+    label: Option<&str>,
+    trace: bool,
+) -> String {
+    match operand {
+        Operand::Register(register) => register.to_string(),
+        Operand::SegmentRegister(segment_register) => segment_register.to_string(),
+        Operand::Memory(address) => format_memory(address),
+        Operand::Immediate(data) if trace => format_unsigned_immediate(decoded, data),
+        Operand::Immediate(data) => data.to_string(),
+        Operand::Relative(displacement) => match label {
+            Some(label) => label.to_string(),
+            // NASM's `$` is the start of the current instruction.
+            None => which_relative(decoded, displacement, "$"),
+        },
+        Operand::Intersegment { segment, offset } => format!("{}:{}", segment, offset),
+    }
+}
+
+fn is_accumulator_exchange(decoded: &DecodedInstruction) -> bool {
+    let accumulator = Operand::Register(EffectiveAddressCalculation::AX);
+
+    decoded.operands.contains(&accumulator)
+        && decoded
+            .operands
+            .iter()
+            .all(|operand| matches!(operand, Operand::Register(_)))
+}
+
+/// Formats one decoded instruction the way simulator traces show it: NASM,
+/// with immediates unsigned and widths only where no register implies them.
+pub(crate) fn format_trace_instruction(decoded: &DecodedInstruction) -> String {
+    format_nasm(decoded, None, true)
+}
+
+fn format_nasm(decoded: &DecodedInstruction, label: Option<&str>, trace: bool) -> String {
+    let mut operands: Vec<String> = decoded
+        .operands
+        .iter()
+        .map(|operand| format_operand(decoded, operand, label, trace))
+        .collect();
+
+    let width = decoded
+        .width
+        .map(|width| width.to_string())
+        .unwrap_or_default();
+    let memory_operand = matches!(decoded.operands.first(), Some(Operand::Memory(_)));
+
+    // Spell out the width wherever no register operand implies it. Listings
+    // also keep it on register forms, which pick the long encoding.
+    match decoded.instruction {
+        Instruction::MovImmediateToRegisterMemory
+        | Instruction::AddImmediateToRegisterMemory
+        | Instruction::AdcImmediateToRegisterMemory
+        | Instruction::SubImmediateFromRegisterMemory
+        | Instruction::SbbImmediateFromRegisterMemory
+        | Instruction::CmpImmediateWithRegisterMemory
+        | Instruction::AndImmediateToRegisterMemory
+        | Instruction::TestImmediateDataAndRegisterMemory
+        | Instruction::OrImmediateToRegisterMemory
+        | Instruction::XorImmediateToRegisterMemory
+        | Instruction::PushRegisterMemory
+        | Instruction::PopRegisterMemory
+        | Instruction::IncRegisterMemory
+        | Instruction::DecRegisterMemory
+            if trace && !memory_operand => {}
+        Instruction::MovImmediateToRegisterMemory => {
+            operands[1] = format!("{} {}", width, operands[1]);
+        }
+        Instruction::AddImmediateToRegisterMemory
+        | Instruction::AdcImmediateToRegisterMemory
+        | Instruction::SubImmediateFromRegisterMemory
+        | Instruction::SbbImmediateFromRegisterMemory
+        | Instruction::CmpImmediateWithRegisterMemory
+        | Instruction::AndImmediateToRegisterMemory
+        | Instruction::TestImmediateDataAndRegisterMemory
+        | Instruction::OrImmediateToRegisterMemory
+        | Instruction::XorImmediateToRegisterMemory
+        | Instruction::PushRegisterMemory
+        | Instruction::PopRegisterMemory
+        | Instruction::IncRegisterMemory
+        | Instruction::DecRegisterMemory => {
+            operands[0] = format!("{} {}", width, operands[0]);
+        }
+        // Exchanging AX with another register has a one-byte form too.
+        Instruction::XchgRegisterMemoryWithRegister
+            if !trace && is_accumulator_exchange(decoded) =>
+        {
+            operands[0] = format!("{} {}", width, operands[0]);
+        }
+        Instruction::NegChangeSign
+        | Instruction::MultiplyUnsigned
+        | Instruction::IntegerMultiplySigned
+        | Instruction::DivideUnsigned
+        | Instruction::IntegerDivideSigned
+        | Instruction::NotInvert
+        | Instruction::ShiftLogicalLeft
+        | Instruction::ShiftLogicalRight
+        | Instruction::ShiftArithmeticRight
+        | Instruction::RotateLeft
+        | Instruction::RotateRight
+        | Instruction::RotateThroughCarryLeft
+        | Instruction::RotateThroughCarryRight
+            if memory_operand =>
+        {
+            operands[0] = format!("{} {}", width, operands[0]);
+        }
+        Instruction::CallIndirectIntersegment | Instruction::JmpIndirectIntersegment => {
+            operands[0] = format!("far {}", operands[0]);
+        }
+        Instruction::JmpDirectWithinSegment => {
+            operands[0] = format!("near {}", operands[0]);
+        }
+        Instruction::JmpDirectWithinSegmentShort => {
+            operands[0] = format!("short {}", operands[0]);
+        }
+        _ if is_default_base(decoded) => operands.clear(),
+        _ => {}
+    }
+
+    let mut parts = format_prefixes(decoded);
+
+    let mnemonic = format_mnemonic(decoded);
+    if !mnemonic.is_empty() {
+        parts.push(mnemonic);
+    }
+
+    join_line(parts, operands)
+}
+
+impl Syntax for Nasm {
+    fn header(&self) -> &'static str {
+        "bits 16"
+    }
+
+    fn format_instruction(&self, decoded: &DecodedInstruction, label: Option<&str>) -> String {
+        let line = format_nasm(decoded, label, false);
+
+        // NASM has no spelling for the encodings it doesn't pick itself, or
+        // for esc, which it only knows as the x87 mnemonics. Those are kept
+        // as data to reassemble to the same bytes.
+        match encode(decoded) {
+            Ok(bytes)
+                if decoded.op == Op::Esc
+                    || assemble(&format_nasm(decoded, None, false)).as_ref() != Ok(&bytes) =>
+            {
+                let data: Vec<String> = bytes.iter().map(|byte| format!("{:#04x}", byte)).collect();
+                format!("db {} ; {}", data.join(", "), line)
+            }
+            _ => line,
+        }
+    }
+
+    fn format_byte(&self, byte: u8) -> String {
+        format!("db {:#04x}", byte)
+    }
+}
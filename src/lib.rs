@@ -0,0 +1,35 @@
+pub mod assembler;
+pub mod clocks;
+pub mod decoder;
+pub mod encoder;
+pub mod formatter;
+pub mod processor;
+pub mod simulator;
+pub mod verifier;
+
+pub use assembler::assemble;
+pub use assembler::error::AssembleError;
+pub use clocks::{estimate_clocks, format_clocks, which_cpu, Clocks, Cpu, Execution};
+pub use decoder::constants::{EffectiveAddressCalculation, Instruction, Op, SegmentRegister};
+pub use decoder::decoded_instruction::{
+    DecodedInstruction, Encoding, MemoryAddress, Operand, Prefixes, Width,
+};
+pub use decoder::error::DecodeError;
+pub use encoder::encode;
+pub use encoder::error::EncodeError;
+pub use formatter::{
+    format_instruction, format_instruction_with_label, format_undecodable, which_syntax, Att, Masm,
+    Nasm, Syntax,
+};
+pub use processor::{
+    decode, decode_bin, process_bin, process_bin_as, process_bin_listing, process_bin_with_labels,
+    process_bin_with_labels_as, Decoder,
+};
+pub use simulator::debugger::{Condition, Debugger, Watchpoint};
+pub use simulator::error::{ExpressionError, SimulateError};
+pub use simulator::expression::Expression;
+pub use simulator::flags::{Flag, Flags};
+pub use simulator::memory::{Memory, MemoryAccess, MEMORY_SIZE};
+pub use simulator::registers::Registers;
+pub use simulator::{exec_bin, which_number, Simulator, TraceOptions};
+pub use verifier::{verify, VerifyError};
@@ -1,43 +1,190 @@
-mod decoder;
-mod processor;
-
-use processor::process_bin;
+use instruction_decoder::{
+    process_bin_as, process_bin_listing, process_bin_with_labels_as, which_cpu, which_number,
+    which_syntax, Cpu, Debugger, Simulator, Syntax, TraceOptions, MEMORY_SIZE,
+};
 use std::env;
 use std::fs;
+use std::io;
+
+// `START:LENGTH`, an address range.
+fn which_range(text: &str) -> Option<(usize, usize)> {
+    let (start, length) = text.split_once(':')?;
+    Some((which_number(start)?, which_number(length)?))
+}
+
+// `START:WIDTHxHEIGHT`, an image's first pixel and size.
+fn which_region(text: &str) -> Option<(usize, usize, usize)> {
+    let (start, size) = text.split_once(':')?;
+    let (width, height) = size.split_once('x')?;
+    Some((
+        which_number(start)?,
+        which_number(width)?,
+        which_number(height)?,
+    ))
+}
 
 /*
  * 137  -> 10001001  -> 100010|d|w  -> 100010 (mov) | d = 0     | w = 1
  * 217  -> 11011001  -> mod|reg|r/m -> mod = 11     | reg = 011 | r/m = 001
  */
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    // `--labels` turns on the two-pass listing with jump targets as labels.
+    let labels = args.iter().any(|arg| arg == "--labels");
+
+    // `--listing` prints offsets and raw bytes next to every line instead of
+    // the binary dump.
+    let listing = args.iter().any(|arg| arg == "--listing");
 
-    if args.len() != 2 {
-        println!("Expected 1 argument, found {}", args.len() - 1);
+    // `--exec` runs the instructions and prints a register trace instead.
+    // `--no-ip` leaves IP out of it, `--max-steps=N` stops it after N
+    // instructions.
+    let exec = args.iter().any(|arg| arg == "--exec");
+
+    // `--debug` steps through the instructions with commands read from
+    // stdin, traced like `--exec`.
+    let debug = args.iter().any(|arg| arg == "--debug");
+
+    // `--clocks=8086|8088` adds estimated clocks and a running total to the
+    // listing and the trace.
+    let clocks: Option<Cpu> = match args.iter().find_map(|arg| arg.strip_prefix("--clocks=")) {
+        Some(cpu_name) => {
+            let Some(cpu) = which_cpu(cpu_name) else {
+                println!("Unknown processor {}, expected 8086 or 8088", cpu_name);
+                return;
+            };
+            Some(cpu)
+        }
+        None => None,
+    };
+
+    // After `--exec`, `--dump=FILE` writes the memory to a file, all of it or
+    // the `--dump-range=START:LENGTH`. `--image=FILE` renders the
+    // `--image-region=START:WIDTHxHEIGHT`, by default `0:64x64`, as a PPM
+    // with four bytes to a pixel.
+    let dump_path = args.iter().find_map(|arg| arg.strip_prefix("--dump="));
+    let dump_range = match args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--dump-range="))
+    {
+        Some(range) => {
+            let Some(range) = which_range(range) else {
+                println!("Invalid dump range {}, expected START:LENGTH", range);
+                return;
+            };
+            range
+        }
+        None => (0, MEMORY_SIZE),
+    };
+    let image_path = args.iter().find_map(|arg| arg.strip_prefix("--image="));
+    let image_region = match args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--image-region="))
+    {
+        Some(region) => {
+            let Some(region) = which_region(region) else {
+                println!(
+                    "Invalid image region {}, expected START:WIDTHxHEIGHT",
+                    region
+                );
+                return;
+            };
+            region
+        }
+        None => (0, 64, 64),
+    };
+
+    let trace_options = TraceOptions {
+        ip: !args.iter().any(|arg| arg == "--no-ip"),
+        max_steps: args
+            .iter()
+            .find_map(|arg| arg.strip_prefix("--max-steps="))
+            .and_then(|steps| steps.parse().ok()),
+        clocks,
+    };
+
+    // `--syntax=nasm|masm|att` picks the listing syntax, NASM by default.
+    let syntax_name = args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--syntax="))
+        .unwrap_or("nasm");
+    let Some(syntax): Option<&dyn Syntax> = which_syntax(syntax_name) else {
+        println!("Unknown syntax {}, expected nasm, masm or att", syntax_name);
+        return;
+    };
+
+    let paths: Vec<&String> = args.iter().filter(|arg| !arg.starts_with("--")).collect();
+
+    if paths.len() != 1 {
+        println!("Expected 1 file argument, found {}", paths.len());
         return;
     }
 
-    let file_path: &str = &args[1].as_str();
-    println!("; Read from the binary\n");
+    let file_path: &str = paths[0].as_str();
+    let contents: Vec<u8> = fs::read(file_path).expect("Error reading file");
+
+    if debug {
+        let mut debugger = Debugger::new(Simulator::new(), &contents, &trace_options);
+        debugger
+            .run(io::stdin().lock(), io::stdout().lock())
+            .expect("Error reading commands");
+        return;
+    }
+
+    if exec {
+        let mut simulator = Simulator::new();
+        print!("{}", simulator.run(&contents, file_path, &trace_options));
+
+        if let Some(dump_path) = dump_path {
+            let (start, length) = dump_range;
+            fs::write(dump_path, simulator.memory.slice(start, length))
+                .expect("Error writing file");
+        }
 
-    let contents: Vec<u8> = fs::read(&file_path).expect("Error reading file");
+        if let Some(image_path) = image_path {
+            let (start, width, height) = image_region;
+            fs::write(
+                image_path,
+                simulator.memory.render_ppm(start, width, height),
+            )
+            .expect("Error writing file");
+        }
+        return;
+    }
+
+    if listing {
+        print!("{}", process_bin_listing(&contents, syntax, labels, clocks));
+        return;
+    }
+
+    println!("; Read from the binary\n");
     for bytes in contents.chunks(8) {
         for byte in bytes {
             print!("{:08b} ", byte);
         }
-        print!("\n")
+        println!()
     }
     println!("; raw data\n===\n");
 
-    let processed: String = process_bin(&contents);
+    let processed: String = if labels {
+        process_bin_with_labels_as(&contents, syntax)
+    } else {
+        process_bin_as(&contents, syntax)
+    };
     println!("{}", processed);
 }
 
 #[cfg(test)]
 mod tests {
-    use std::{ffi::c_void, fs::File, io::Write, process::Command};
-
     use super::*;
+    use instruction_decoder::{
+        assemble, decode, decode_bin, encode, exec_bin, format_instruction, process_bin,
+        process_bin_with_labels, verify, AssembleError, Att, DecodeError, DecodedInstruction,
+        Decoder, EffectiveAddressCalculation, EncodeError, Encoding, Expression, Flag, Instruction,
+        Masm, Memory, MemoryAddress, Nasm, Op, Operand, Prefixes, SegmentRegister, Simulator,
+        VerifyError, Width,
+    };
 
     fn normalize_asm(content: &str) -> String {
         content
@@ -48,49 +195,11 @@ mod tests {
             .join("\n")
     }
 
-    fn rebuild_asm(content: &String, bin_file_path: &String) -> String {
-        if fs::metadata("./out").is_err() {
-            fs::create_dir_all("./out").expect("Error creating './out' directory");
-        }
-
-        let test_name = bin_file_path.split("/").last().unwrap();
-
-        let asm_file_path = format!("./out/test_{}.asm", test_name);
-        let out_file_path = format!("./out/test_{}.o", test_name);
-
-        let mut asm_file = File::create(&asm_file_path).unwrap();
-        asm_file.write(content.as_bytes()).unwrap();
-
-        Command::new("nasm")
-            .args([&asm_file_path, "-o", &out_file_path])
-            .output()
-            .expect("Failed to build asm");
-
-        out_file_path
-    }
-
     fn compare_bin(bin_file: &str) {
         let original_bin_content: Vec<u8> = fs::read(bin_file).expect("Error reading file");
-        let original_bin_content_processed: String = process_bin(&original_bin_content);
 
-        let original_bin_content_processed_rebuild: String =
-            rebuild_asm(&original_bin_content_processed, &bin_file.to_string());
-        let rebuilded_bin_content: Vec<u8> =
-            fs::read(original_bin_content_processed_rebuild).expect("Error reading file");
-
-        if original_bin_content.len() != rebuilded_bin_content.len() {
-            assert!(false)
-        }
-
-        unsafe {
-            if libc::memcmp(
-                original_bin_content.as_ptr() as *const c_void,
-                rebuilded_bin_content.as_ptr() as *const c_void,
-                original_bin_content.len(),
-            ) == 0
-            {
-                assert!(true)
-            }
+        if let Err(error) = verify(&original_bin_content) {
+            panic!("{}: {}", bin_file, error);
         }
     }
 
@@ -110,6 +219,62 @@ mod tests {
         );
     }
 
+    fn compare_assemble(asm_file: &str, bin_file: &str) {
+        let original_asm_content: String =
+            fs::read_to_string(asm_file).expect("Error reading file");
+        let original_bin_content: Vec<u8> = fs::read(bin_file).expect("Error reading file");
+
+        let assembled = assemble(&original_asm_content).expect("Error assembling file");
+        assert_eq!(assembled, original_bin_content);
+
+        let reassembled = assemble(&process_bin_with_labels(&original_bin_content))
+            .expect("Error assembling decoded file");
+        assert_eq!(reassembled, original_bin_content);
+    }
+
+    fn compare_encode(bin_file: &str) {
+        let original_bin_content: Vec<u8> = fs::read(bin_file).expect("Error reading file");
+
+        for decoded in decode_bin(&original_bin_content).expect("Error decoding file") {
+            let original_bytes = &original_bin_content[decoded.offset..][..decoded.length];
+
+            assert_eq!(
+                encode(&decoded).as_deref(),
+                Ok(original_bytes),
+                "{} at offset {}",
+                format_instruction(&decoded),
+                decoded.offset
+            );
+        }
+    }
+
+    // The reference traces predate IP tracing.
+    const REFERENCE_TRACE: TraceOptions = TraceOptions {
+        ip: false,
+        max_steps: None,
+        clocks: None,
+    };
+
+    fn compare_exec(txt_file: &str, bin_file: &str) {
+        let original_txt_content: String = fs::read_to_string(txt_file)
+            .expect("Error reading file")
+            .replace("\r\n", "\n");
+        let original_bin_content: Vec<u8> = fs::read(bin_file).expect("Error reading file");
+
+        // The heading names the file the trace was made from.
+        let name = original_txt_content
+            .lines()
+            .next()
+            .and_then(|line| line.strip_prefix("--- "))
+            .and_then(|line| line.strip_suffix(" execution ---"))
+            .expect("Error reading heading");
+
+        assert_eq!(
+            exec_bin(&original_bin_content, name, &REFERENCE_TRACE),
+            original_txt_content
+        );
+    }
+
     #[test]
     fn asm_cmp_listing_0037_single_register_mov() {
         const ASM_FILE_PATH: &str = "./vendor/listing_0037_single_register_mov.asm";
@@ -142,6 +307,628 @@ mod tests {
         compare_asm(ASM_FILE_PATH, BIN_FILE_PATH)
     }
 
+    #[test]
+    fn decode_listing_0039_more_movs() {
+        const BIN_FILE_PATH: &str = "./vendor/listing_0039_more_movs";
+
+        let contents: Vec<u8> = fs::read(BIN_FILE_PATH).expect("Error reading file");
+        let decoded = decode_bin(&contents).expect("Error decoding file");
+
+        // mov ch, -12
+        assert_eq!(decoded[3].op, Op::Mov);
+        assert_eq!(decoded[3].instruction, Instruction::MovImmediateToRegister);
+        assert_eq!(decoded[3].width, Some(Width::Byte));
+        assert_eq!(
+            decoded[3].operands,
+            vec![
+                Operand::Register(EffectiveAddressCalculation::CH),
+                Operand::Immediate(-12)
+            ]
+        );
+        assert_eq!((decoded[3].offset, decoded[3].length), (6, 2));
+
+        // mov dx, [bp]
+        assert_eq!(
+            decoded[10].operands,
+            vec![
+                Operand::Register(EffectiveAddressCalculation::DX),
+                Operand::Memory(MemoryAddress {
+                    base: Some(EffectiveAddressCalculation::Bp),
+                    displacement: 0,
+                    segment: None,
+                })
+            ]
+        );
+        assert_eq!((decoded[10].offset, decoded[10].length), (24, 3));
+    }
+
+    #[test]
+    fn decode_at_offset_listing_0039_more_movs() {
+        const BIN_FILE_PATH: &str = "./vendor/listing_0039_more_movs";
+
+        let contents: Vec<u8> = fs::read(BIN_FILE_PATH).expect("Error reading file");
+        let decoded = decode(&contents, 24).unwrap();
+
+        assert_eq!(format_instruction(&decoded), "mov dx, [bp]");
+        assert_eq!(
+            decode(&contents, contents.len()),
+            Err(DecodeError::TruncatedInstruction {
+                offset: contents.len()
+            })
+        );
+        assert_eq!(
+            Decoder::new(&contents)
+                .map(|decoded| decoded.unwrap().length)
+                .sum::<usize>(),
+            contents.len()
+        );
+    }
+
+    #[test]
+    fn decode_errors_carry_offset() {
+        // mov cx, bx cut off before its mod/reg/rm byte
+        assert_eq!(
+            decode(&[0x89, 0xd9, 0x89], 2),
+            Err(DecodeError::TruncatedInstruction { offset: 2 })
+        );
+        // 0x60 has no meaning on the 8086
+        assert_eq!(
+            decode(&[0x90, 0x60], 1),
+            Err(DecodeError::UndefinedOpcode {
+                offset: 1,
+                opcode: 0x60
+            })
+        );
+        // lea cannot take a register source
+        assert_eq!(
+            decode(&[0x8d, 0xc3], 0),
+            Err(DecodeError::InvalidModRm {
+                offset: 0,
+                mod_rm: 0xc3
+            })
+        );
+        // iteration picks up again at the byte after the error
+        let mut decoder = Decoder::new(&[0x90, 0x81, 0xc4]);
+        assert!(decoder.next().unwrap().is_ok());
+        assert_eq!(
+            decoder.next(),
+            Some(Err(DecodeError::TruncatedInstruction { offset: 1 }))
+        );
+        assert_eq!(
+            decoder.next(),
+            Some(Err(DecodeError::TruncatedInstruction { offset: 2 }))
+        );
+        assert_eq!(decoder.next(), None);
+    }
+
+    #[test]
+    fn process_bin_keeps_undecodable_bytes() {
+        assert_eq!(
+            process_bin(&[0x60, 0x89, 0xd9, 0x8d, 0xc3, 0x81]),
+            "bits 16\n\n\
+             db 0x60 ; undefined opcode 0x60 at offset 0\n\
+             mov cx, bx\n\
+             db 0x8d ; invalid mod r/m byte 0xc3 at offset 3\n\
+             ret\n\
+             db 0x81 ; truncated instruction at offset 5\n"
+        );
+    }
+
+    #[test]
+    fn labels_listing_0041_add_sub_cmp_jnz() {
+        const BIN_FILE_PATH: &str = "./vendor/listing_0041_add_sub_cmp_jnz";
+
+        let contents: Vec<u8> = fs::read(BIN_FILE_PATH).expect("Error reading file");
+        let processed = process_bin_with_labels(&contents);
+
+        assert!(processed.contains(
+            "label_0:\njne label_1\njne label_0\nlabel_1:\njne label_0\njne label_1\nlabel_2:\nje label_2\n"
+        ));
+        assert!(processed.ends_with("jcxz label_2\n"));
+    }
+
+    #[test]
+    fn labels_flag_bad_targets() {
+        assert_eq!(
+            process_bin_with_labels(&[0x75, 0x10, 0xeb, 0xff, 0xe2, 0xfa]),
+            "bits 16\n\n\
+             label_0:\n\
+             jne $+18 ; target 18 is outside the buffer\n\
+             jmp short $+1 ; target 3 is inside an instruction\n\
+             loop label_0\n"
+        );
+    }
+
+    #[test]
+    fn format_masm_and_att_syntax() {
+        let lines = [
+            // mov word [bx + si + 4], 12
+            (
+                vec![0xc7, 0x40, 0x04, 0x0c, 0x00],
+                "mov word ptr [bx+si+4], 12",
+                "movw $12, 4(%bx,%si)",
+            ),
+            // mov bx, [3458]
+            (
+                vec![0x8b, 0x1e, 0x82, 0x0d],
+                "mov bx, ds:[3458]",
+                "mov 3458, %bx",
+            ),
+            // shl byte [bp - 2], cl
+            (
+                vec![0xd2, 0x66, 0xfe],
+                "shl byte ptr [bp-2], cl",
+                "shlb %cl, -2(%bp)",
+            ),
+            // in ax, dx
+            (vec![0xed], "in ax, dx", "in (%dx), %ax"),
+            // call far [bx]
+            (vec![0xff, 0x1f], "call dword ptr [bx]", "lcall *(%bx)"),
+            // jne $-2
+            (vec![0x75, 0xfc], "jne $-2", "jne .-2"),
+        ];
+
+        for (bytes, masm, att) in lines {
+            let decoded = decode(&bytes, 0).unwrap();
+            assert_eq!(Masm.format_instruction(&decoded, None), masm);
+            assert_eq!(Att.format_instruction(&decoded, None), att);
+        }
+
+        let contents = fs::read("vendor/listing_0041_add_sub_cmp_jnz").unwrap();
+        let listing = process_bin_with_labels_as(&contents, which_syntax("att").unwrap());
+        assert!(listing.starts_with(".code16\n\nadd (%bx,%si), %bx\n"));
+        assert!(listing.contains("label_0:\njne label_1\njne label_0\n"));
+        assert_eq!(
+            process_bin_as(&[0x60], &Masm),
+            ".8086\n\ndb 060h ; undefined opcode 0x60 at offset 0\n"
+        );
+        assert!(which_syntax("intel").is_none());
+    }
+
+    #[test]
+    fn listing_shows_offsets_and_bytes() {
+        let contents = fs::read("vendor/listing_0041_add_sub_cmp_jnz").unwrap();
+
+        let listing = process_bin_listing(&contents, &Nasm, false, None);
+        assert!(listing.starts_with(
+            "0000  03 18             add bx, [bx + si]\n\
+             0002  03 5e 00          add bx, [bp]\n\
+             0005  83 c6 02          add word si, 2\n"
+        ));
+        assert!(listing.contains("00c9  75 fc             jne $-2\n"));
+
+        let listing = process_bin_listing(&contents, &Nasm, true, None);
+        assert!(listing.contains("label_0:\n00c7  75 02             jne label_1\n"));
+
+        // Undecodable bytes and prefixes show up with the line they belong to.
+        // NASM writes rep ahead of lock, so lock first stays as data.
+        assert_eq!(
+            process_bin_listing(
+                &[0x60, 0xf0, 0xf3, 0xa4, 0x2e, 0x8b, 0x1e, 0x82, 0x0d],
+                &Nasm,
+                false,
+                None
+            ),
+            "0000  60                db 0x60 ; undefined opcode 0x60 at offset 0\n\
+             0001  f0 f3 a4          db 0xf0, 0xf3, 0xa4 ; lock rep movsb\n\
+             0004  2e 8b 1e 82 0d    mov bx, cs:[3458]\n"
+        );
+
+        // The es override wins, the cs ahead of it is kept as it came.
+        let contents = [0x2e, 0x26, 0x8b, 0x07, 0xf3, 0xf3, 0xa4];
+        let decoded = decode(&contents, 0).unwrap();
+        assert_eq!(decoded.prefixes.segment, Some(SegmentRegister::ES));
+        assert_eq!(decoded.prefixes.bytes, vec![0x2e, 0x26]);
+        assert_eq!(encode(&decoded), Ok(contents[..4].to_vec()));
+        assert_eq!(
+            process_bin_listing(&contents, &Nasm, false, None),
+            "0000  2e 26 8b 07       db 0x2e, 0x26, 0x8b, 0x07 ; mov ax, es:[bx]\n\
+             0004  f3 f3 a4          db 0xf3, 0xf3, 0xa4 ; rep movsb\n"
+        );
+        assert_eq!(verify(&contents), Ok(()));
+
+        // NASM only knows esc as the x87 mnemonics.
+        let contents = [0xd8, 0xc0, 0xdf, 0x47, 0x02];
+        assert_eq!(
+            process_bin_listing(&contents, &Nasm, false, None),
+            "0000  d8 c0             db 0xd8, 0xc0 ; esc 0, ax\n\
+             0002  df 47 02          db 0xdf, 0x47, 0x02 ; esc 56, [bx + 2]\n"
+        );
+        assert_eq!(verify(&contents), Ok(()));
+    }
+
+    #[test]
+    fn repeat_prefix_looks_past_other_prefixes() {
+        let contents = [0xf3, 0x2e, 0xa6, 0xf3, 0xf0, 0x26, 0xa4, 0xf2, 0x3e, 0xae];
+        let lines: Vec<String> = Decoder::new(&contents)
+            .map(|decoded| format_instruction(&decoded.unwrap()))
+            .collect();
+        assert_eq!(
+            lines,
+            ["repe cs cmpsb", "lock rep es movsb", "repne ds scasb"]
+        );
+        assert_eq!(verify(&contents), Ok(()));
+    }
+
+    #[test]
+    fn encode_cmp_listing_0037_single_register_mov() {
+        const BIN_FILE_PATH: &str = "./vendor/listing_0037_single_register_mov";
+
+        compare_encode(BIN_FILE_PATH)
+    }
+
+    #[test]
+    fn encode_cmp_listing_0038_many_register_mov() {
+        const BIN_FILE_PATH: &str = "./vendor/listing_0038_many_register_mov";
+
+        compare_encode(BIN_FILE_PATH)
+    }
+
+    #[test]
+    fn encode_cmp_listing_0039_more_movs() {
+        const BIN_FILE_PATH: &str = "./vendor/listing_0039_more_movs";
+
+        compare_encode(BIN_FILE_PATH)
+    }
+
+    #[test]
+    fn encode_cmp_listing_0040_challenge_movs() {
+        const BIN_FILE_PATH: &str = "./vendor/listing_0040_challenge_movs";
+
+        compare_encode(BIN_FILE_PATH)
+    }
+
+    #[test]
+    fn encode_cmp_listing_0041_add_sub_cmp_jnz() {
+        const BIN_FILE_PATH: &str = "./vendor/listing_0041_add_sub_cmp_jnz";
+
+        compare_encode(BIN_FILE_PATH)
+    }
+
+    #[test]
+    fn encode_cmp_listing_0042_completionist_decode() {
+        const BIN_FILE_PATH: &str = "./vendor/listing_0042_completionist_decode";
+
+        compare_encode(BIN_FILE_PATH)
+    }
+
+    #[test]
+    fn encode_cmp_listing_0043_immediate_movs() {
+        const BIN_FILE_PATH: &str = "./vendor/listing_0043_immediate_movs";
+
+        compare_encode(BIN_FILE_PATH)
+    }
+
+    #[test]
+    fn encode_cmp_listing_0044_register_movs() {
+        const BIN_FILE_PATH: &str = "./vendor/listing_0044_register_movs";
+
+        compare_encode(BIN_FILE_PATH)
+    }
+
+    #[test]
+    fn encode_cmp_listing_0045_challenge_register_movs() {
+        const BIN_FILE_PATH: &str = "./vendor/listing_0045_challenge_register_movs";
+
+        compare_encode(BIN_FILE_PATH)
+    }
+
+    #[test]
+    fn encode_hand_built_instructions() {
+        // mov word [bp + di - 300], 7
+        let mut decoded = DecodedInstruction::new(
+            Op::Mov,
+            Instruction::MovImmediateToRegisterMemory,
+            Prefixes::default(),
+            0,
+        );
+        decoded.width = Some(Width::Word);
+        decoded.operands = vec![
+            Operand::Memory(MemoryAddress {
+                base: Some(EffectiveAddressCalculation::BpDi),
+                displacement: -300,
+                segment: None,
+            }),
+            Operand::Immediate(7),
+        ];
+        assert_eq!(
+            encode(&decoded),
+            Ok(vec![0xc7, 0x83, 0xd4, 0xfe, 0x07, 0x00])
+        );
+
+        // add word es:[bp], 5 picks the sign-extended form and a zero disp8
+        decoded.op = Op::Add;
+        decoded.instruction = Instruction::AddImmediateToRegisterMemory;
+        decoded.operands = vec![
+            Operand::Memory(MemoryAddress {
+                base: Some(EffectiveAddressCalculation::Bp),
+                displacement: 0,
+                segment: Some(SegmentRegister::ES),
+            }),
+            Operand::Immediate(5),
+        ];
+        assert_eq!(encode(&decoded), Ok(vec![0x26, 0x83, 0x46, 0x00, 0x05]));
+
+        // add cl, bx mixes widths
+        decoded.instruction = Instruction::AddRegisterMemoryWithRegisterToEither;
+        decoded.operands = vec![
+            Operand::Register(EffectiveAddressCalculation::CL),
+            Operand::Register(EffectiveAddressCalculation::BX),
+        ];
+        assert_eq!(
+            encode(&decoded),
+            Err(EncodeError::InvalidOperands {
+                instruction: Instruction::AddRegisterMemoryWithRegisterToEither
+            })
+        );
+
+        // jne can only reach -128..=127
+        decoded.op = Op::Jne;
+        decoded.instruction = Instruction::JumpOnNotEqual;
+        decoded.operands = vec![Operand::Relative(200)];
+        assert_eq!(
+            encode(&decoded),
+            Err(EncodeError::RelativeOutOfRange {
+                instruction: Instruction::JumpOnNotEqual,
+                displacement: 200
+            })
+        );
+    }
+
+    #[test]
+    fn encode_picks_fields_and_sizes() {
+        let bx = |displacement| {
+            Operand::Memory(MemoryAddress {
+                base: Some(EffectiveAddressCalculation::Bx),
+                displacement,
+                segment: None,
+            })
+        };
+
+        // mov [bp], cx has no mod 00 encoding and takes a zero disp8
+        let mut decoded = DecodedInstruction::new(
+            Op::Mov,
+            Instruction::MovRegisterMemoryToFromRegister,
+            Prefixes::default(),
+            0,
+        );
+        decoded.width = Some(Width::Word);
+        decoded.operands = vec![
+            Operand::Memory(MemoryAddress {
+                base: Some(EffectiveAddressCalculation::Bp),
+                displacement: 0,
+                segment: None,
+            }),
+            Operand::Register(EffectiveAddressCalculation::CX),
+        ];
+        assert_eq!(encode(&decoded), Ok(vec![0x89, 0x4e, 0x00]));
+
+        // mov cx, [bx + 0] sets d and drops the displacement
+        decoded.operands = vec![Operand::Register(EffectiveAddressCalculation::CX), bx(0)];
+        assert_eq!(encode(&decoded), Ok(vec![0x8b, 0x0f]));
+
+        // mov cl, [bx + 300] clears w and needs a disp16
+        decoded.width = Some(Width::Byte);
+        decoded.operands = vec![Operand::Register(EffectiveAddressCalculation::CL), bx(300)];
+        assert_eq!(encode(&decoded), Ok(vec![0x8a, 0x8f, 0x2c, 0x01]));
+
+        // mov cx, [4660] is a direct address, mod 00 with r/m 110
+        decoded.width = Some(Width::Word);
+        decoded.operands = vec![
+            Operand::Register(EffectiveAddressCalculation::CX),
+            Operand::Memory(MemoryAddress {
+                base: None,
+                displacement: 4660,
+                segment: None,
+            }),
+        ];
+        assert_eq!(encode(&decoded), Ok(vec![0x8b, 0x0e, 0x34, 0x12]));
+
+        // sub word [bx - 2], -3 sign-extends a byte, 300 doesn't fit one
+        decoded.op = Op::Sub;
+        decoded.instruction = Instruction::SubImmediateFromRegisterMemory;
+        decoded.operands = vec![bx(-2), Operand::Immediate(-3)];
+        assert_eq!(encode(&decoded), Ok(vec![0x83, 0x6f, 0xfe, 0xfd]));
+        decoded.operands = vec![bx(-2), Operand::Immediate(300)];
+        assert_eq!(encode(&decoded), Ok(vec![0x81, 0x6f, 0xfe, 0x2c, 0x01]));
+    }
+
+    #[test]
+    fn encode_keeps_the_decoded_form() {
+        // Each has a shorter or differently spelled twin NASM would pick.
+        let forms: [(&[u8], &str); 5] = [
+            (&[0x8b, 0x87, 0x00, 0x00], "mov ax, [bx]"),
+            (&[0x02, 0xc1], "add al, cl"),
+            (&[0x82, 0xc0, 0x01], "add byte al, 1"),
+            (&[0x8a, 0x06, 0x13, 0xf1], "mov al, [61715]"),
+            (&[0x81, 0xc0, 0x05, 0x00], "add word ax, 5"),
+        ];
+
+        for (contents, line) in forms {
+            let decoded = decode(contents, 0).unwrap();
+            assert_eq!(encode(&decoded).as_deref(), Ok(contents));
+
+            // The listing keeps them as data, with the instruction alongside.
+            let data: Vec<String> = contents
+                .iter()
+                .map(|byte| format!("{:#04x}", byte))
+                .collect();
+            assert_eq!(
+                format_instruction(&decoded),
+                format!("db {} ; {}", data.join(", "), line)
+            );
+        }
+
+        // NASM's own choices are written as instructions.
+        let decoded = decode(&[0x8b, 0x47, 0x7f], 0).unwrap();
+        assert_eq!(decoded.encoding, Encoding::default());
+        assert_eq!(format_instruction(&decoded), "mov ax, [bx + 127]");
+    }
+
+    #[test]
+    fn verify_reports_first_difference() {
+        // mov bx, cx with d = 1, add ax, 5 through 0x81 and mov al, [61715]
+        // through 0x8a keep the form they were decoded from
+        assert_eq!(verify(&[0x90, 0x8b, 0xd9]), Ok(()));
+        assert_eq!(verify(&[0x81, 0xc0, 0x05, 0x00]), Ok(()));
+        assert_eq!(verify(&[0x8a, 0x06, 0x13, 0xf1]), Ok(()));
+        // mov ax, ds through sr field 111, whose high bit the 8086 ignores,
+        // has no spelling that gives it back
+        assert_eq!(
+            verify(&[0x90, 0x8c, 0xf8]),
+            Err(VerifyError::Mismatch {
+                offset: 2,
+                expected: Some(0xf8),
+                found: Some(0xd8),
+                line: String::from("mov ax, ds"),
+            })
+        );
+        // dec word si keeps the long form it was decoded from
+        assert_eq!(verify(&[0xff, 0xce]), Ok(()));
+        // bytes kept as db lines rebuild as themselves
+        assert_eq!(verify(&[0x60, 0x89, 0xd9, 0x81]), Ok(()));
+    }
+
+    #[test]
+    fn assemble_listing_0037_single_register_mov() {
+        const ASM_FILE_PATH: &str = "./vendor/listing_0037_single_register_mov.asm";
+        const BIN_FILE_PATH: &str = "./vendor/listing_0037_single_register_mov";
+
+        compare_assemble(ASM_FILE_PATH, BIN_FILE_PATH)
+    }
+
+    #[test]
+    fn assemble_listing_0038_many_register_mov() {
+        const ASM_FILE_PATH: &str = "./vendor/listing_0038_many_register_mov.asm";
+        const BIN_FILE_PATH: &str = "./vendor/listing_0038_many_register_mov";
+
+        compare_assemble(ASM_FILE_PATH, BIN_FILE_PATH)
+    }
+
+    #[test]
+    fn assemble_listing_0039_more_movs() {
+        const ASM_FILE_PATH: &str = "./vendor/listing_0039_more_movs.asm";
+        const BIN_FILE_PATH: &str = "./vendor/listing_0039_more_movs";
+
+        compare_assemble(ASM_FILE_PATH, BIN_FILE_PATH)
+    }
+
+    #[test]
+    fn assemble_listing_0040_challenge_movs() {
+        const ASM_FILE_PATH: &str = "./vendor/listing_0040_challenge_movs.asm";
+        const BIN_FILE_PATH: &str = "./vendor/listing_0040_challenge_movs";
+
+        compare_assemble(ASM_FILE_PATH, BIN_FILE_PATH)
+    }
+
+    #[test]
+    fn assemble_listing_0041_add_sub_cmp_jnz() {
+        const ASM_FILE_PATH: &str = "./vendor/listing_0041_add_sub_cmp_jnz.asm";
+        const BIN_FILE_PATH: &str = "./vendor/listing_0041_add_sub_cmp_jnz";
+
+        compare_assemble(ASM_FILE_PATH, BIN_FILE_PATH)
+    }
+
+    #[test]
+    fn assemble_listing_0042_completionist_decode() {
+        const ASM_FILE_PATH: &str = "./vendor/listing_0042_completionist_decode.asm";
+        const BIN_FILE_PATH: &str = "./vendor/listing_0042_completionist_decode";
+
+        compare_assemble(ASM_FILE_PATH, BIN_FILE_PATH)
+    }
+
+    #[test]
+    fn assemble_listing_0043_immediate_movs() {
+        const ASM_FILE_PATH: &str = "./vendor/listing_0043_immediate_movs.asm";
+        const BIN_FILE_PATH: &str = "./vendor/listing_0043_immediate_movs";
+
+        compare_assemble(ASM_FILE_PATH, BIN_FILE_PATH)
+    }
+
+    #[test]
+    fn assemble_listing_0044_register_movs() {
+        const ASM_FILE_PATH: &str = "./vendor/listing_0044_register_movs.asm";
+        const BIN_FILE_PATH: &str = "./vendor/listing_0044_register_movs";
+
+        compare_assemble(ASM_FILE_PATH, BIN_FILE_PATH)
+    }
+
+    #[test]
+    fn assemble_listing_0045_challenge_register_movs() {
+        const ASM_FILE_PATH: &str = "./vendor/listing_0045_challenge_register_movs.asm";
+        const BIN_FILE_PATH: &str = "./vendor/listing_0045_challenge_register_movs";
+
+        compare_assemble(ASM_FILE_PATH, BIN_FILE_PATH)
+    }
+
+    #[test]
+    fn long_register_forms_reassemble() {
+        // dec si, inc si, push ax, pop dx, xchg ax, dx, xchg dx, ax and
+        // xchg ax, ax, each through the long register/memory form
+        let contents: Vec<u8> = vec![
+            0xff, 0xce, 0xff, 0xc6, 0xff, 0xf0, 0x8f, 0xc2, 0x87, 0xc2, 0x87, 0xd0, 0x87, 0xc0,
+        ];
+        let listing = process_bin_with_labels(&contents);
+
+        assert!(listing.contains("dec word si\n"));
+        assert!(listing.contains("push word ax\n"));
+        assert!(listing.contains("xchg word dx, ax\n"));
+        assert_eq!(assemble(&listing), Ok(contents));
+
+        // without the size the short forms come back
+        assert_eq!(
+            assemble("inc si\npush ax\nxchg ax, dx"),
+            Ok(vec![0x46, 0x50, 0x92])
+        );
+    }
+
+    #[test]
+    fn assemble_labels_data_and_errors() {
+        assert_eq!(
+            assemble(
+                "bits 16\n\
+                 start: mov cx, 3 ; count\n\
+                 again:\n\
+                 dec cx\n\
+                 jnz again\n\
+                 jmp done\n\
+                 db 1, -1, 0x7f\n\
+                 dw start, 4660\n\
+                 done: ret"
+            ),
+            Ok(vec![
+                0xb9, 0x03, 0x00, 0x49, 0x75, 0xfd, 0xeb, 0x07, 0x01, 0xff, 0x7f, 0x00, 0x00, 0x34,
+                0x12, 0xc3
+            ])
+        );
+        assert_eq!(
+            assemble("jmp nowhere"),
+            Err(AssembleError::UndefinedLabel {
+                line: 1,
+                label: String::from("nowhere")
+            })
+        );
+        assert_eq!(
+            assemble("pusha"),
+            Err(AssembleError::UnknownMnemonic {
+                line: 1,
+                mnemonic: String::from("pusha")
+            })
+        );
+        assert_eq!(assemble("nop\nxchg ax, ax"), Ok(vec![0x90, 0x90]));
+        assert_eq!(
+            assemble("mov ax, [bx + bp]"),
+            Err(AssembleError::Syntax {
+                line: 1,
+                text: String::from("mov ax, [bx + bp]")
+            })
+        );
+        assert_eq!(
+            assemble("mov word al, 1"),
+            Err(AssembleError::InvalidOperands { line: 1 })
+        );
+    }
+
     #[test]
     fn bin_cmp_listing_0037_single_register_mov() {
         const BIN_FILE_PATH: &str = "./vendor/listing_0037_single_register_mov";
@@ -178,7 +965,6 @@ mod tests {
     }
 
     #[test]
-    #[ignore]
     fn bin_cmp_listing_0042_completionist_decode() {
         const BIN_FILE_PATH: &str = "./vendor/listing_0042_completionist_decode";
 
@@ -200,10 +986,537 @@ mod tests {
     }
 
     #[test]
-    #[ignore]
-    fn bin_cmp_listing_0045_challege_register_movs() {
-        const BIN_FILE_PATH: &str = "./vendor/listing_0045_challege_register_movs";
+    fn bin_cmp_listing_0045_challenge_register_movs() {
+        const BIN_FILE_PATH: &str = "./vendor/listing_0045_challenge_register_movs";
 
         compare_bin(BIN_FILE_PATH)
     }
+
+    #[test]
+    fn exec_stops_at_unsupported_instruction() {
+        // mov ax, 1 ; mov ch, -12 ; push ax
+        let trace = exec_bin(
+            &[0xb8, 0x01, 0x00, 0xb5, 0xf4, 0x50],
+            "test",
+            &REFERENCE_TRACE,
+        );
+
+        assert_eq!(
+            trace,
+            "--- test execution ---\n\
+             mov ax, 1 ; ax:0x0->0x1 \n\
+             mov ch, 244 ; cx:0x0->0xf400 \n\
+             ; cannot execute `push ax` at offset 5\n\
+             \n\
+             Final registers:\n      \
+             ax: 0x0001 (1)\n      \
+             cx: 0xf400 (62464)\n\
+             \n"
+        );
+    }
+
+    #[test]
+    fn exec_tracks_flags_and_follows_branches() {
+        let contents = assemble(
+            "mov bx, -4093\n\
+             mov cx, 3841\n\
+             sub bx, cx\n\
+             mov sp, 998\n\
+             mov bp, 999\n\
+             cmp bp, sp\n\
+             add bp, 1027\n\
+             sub bp, 2026\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            exec_bin(&contents, "test", &REFERENCE_TRACE),
+            "--- test execution ---\n\
+             mov bx, 61443 ; bx:0x0->0xf003 \n\
+             mov cx, 3841 ; cx:0x0->0xf01 \n\
+             sub bx, cx ; bx:0xf003->0xe102 flags:->S \n\
+             mov sp, 998 ; sp:0x0->0x3e6 \n\
+             mov bp, 999 ; bp:0x0->0x3e7 \n\
+             cmp bp, sp ; flags:S-> \n\
+             add bp, 1027 ; bp:0x3e7->0x7ea \n\
+             sub bp, 2026 ; bp:0x7ea->0x0 flags:->PZ \n\
+             \n\
+             Final registers:\n      \
+             bx: 0xe102 (57602)\n      \
+             cx: 0x0f01 (3841)\n      \
+             sp: 0x03e6 (998)\n   \
+             flags: PZ\n\
+             \n"
+        );
+
+        // Adds 10 three times with loop, then jumps back for a fourth.
+        let contents = assemble(
+            "mov cx, 3\n\
+             top:\n\
+             add ax, 10\n\
+             loop top\n\
+             mov cx, 1\n\
+             cmp ax, 40\n\
+             jne top\n\
+             add ax, -40\n",
+        )
+        .unwrap();
+        let trace = exec_bin(&contents, "test", &REFERENCE_TRACE);
+
+        assert_eq!(trace.matches("loop $-3").count(), 4);
+        assert_eq!(trace.matches("jne $-11 ; \n").count(), 2);
+        assert!(trace.contains("cmp ax, 40 ; flags:P->CPS \n"));
+        assert!(trace.contains("add ax, -40 ; ax:0x28->0x0 flags:PZ->CPAZ \n"));
+        assert!(trace.ends_with("Final registers:\n      cx: 0x0001 (1)\n   flags: CPAZ\n\n"));
+
+        // 0x8000 - 0x7fff with a borrow in is -65536 signed, an overflow.
+        let contents = assemble(
+            "mov ax, 32768\n\
+             sub bx, 1\n\
+             sbb ax, 32767\n",
+        )
+        .unwrap();
+        let trace = exec_bin(&contents, "test", &REFERENCE_TRACE);
+
+        assert!(trace.contains("sbb ax, 32767 ; ax:0x8000->0x0 flags:CPAS->PAZO \n"));
+    }
+
+    #[test]
+    fn exec_memory_through_segments() {
+        let contents = assemble(
+            "mov ax, 4096\n\
+             mov ds, ax\n\
+             mov ax, 8192\n\
+             mov ss, ax\n\
+             mov bx, 65534\n\
+             mov word [bx + 4], 4660\n\
+             mov cx, [2]\n\
+             mov bp, 2\n\
+             mov dx, [bp]\n\
+             mov [bp], cx\n\
+             add [bp], cx\n\
+             mov si, [bp]\n\
+             mov di, ds:[bp]\n\
+             mov byte [bx + si], 255\n",
+        )
+        .unwrap();
+
+        let mut simulator = Simulator::new();
+        let trace = simulator.run(&contents, "test", &REFERENCE_TRACE);
+
+        // [bx + 4] wraps to offset 2 of DS, [bp] defaults to SS.
+        assert!(trace.contains("mov cx, [2] ; cx:0x0->0x1234 \n"));
+        assert!(trace.contains("mov dx, [bp] ; \n"));
+        assert!(trace.contains("mov si, [bp] ; si:0x0->0x2468 \n"));
+        assert!(trace.contains("mov di, ds:[bp] ; di:0x0->0x1234 \n"));
+        assert_eq!(simulator.memory.slice(0x10002, 2), [0x34, 0x12]);
+        assert_eq!(simulator.memory.slice(0x20002, 2), [0x68, 0x24]);
+        assert_eq!(simulator.memory.read_byte(0x10000 + 0x2466), 0xff);
+        assert_eq!(simulator.memory.read(0x1000, 0x2467, &Width::Byte), 0);
+
+        // A word at offset 0xffff takes its high byte from offset 0.
+        let mut memory = Memory::new();
+        memory.write(0xffff, 0xffff, &Width::Word, 0xbeef);
+        assert_eq!(memory.read_byte(0x0ffef), 0xef);
+        assert_eq!(memory.read_byte(0xffff0), 0xbe);
+        assert_eq!(Memory::physical(0xffff, 0x0010), 0);
+    }
+
+    #[test]
+    fn debugger_steps_over_calls_and_stops_at_breakpoints() {
+        let contents = assemble(
+            "mov sp, 256\n\
+             call add_one\n\
+             call add_one\n\
+             hlt\n\
+             add_one:\n\
+             add ax, 1\n\
+             ret\n",
+        )
+        .unwrap();
+
+        let script = "s\nn\ns\ns\nb 0xa\nl\nc\nset ip 6\nc\nx ss:0xfe 2\nq\n";
+        let mut output: Vec<u8> = Vec::new();
+        let mut debugger = Debugger::new(Simulator::new(), &contents, &TraceOptions::default());
+        debugger.run(script.as_bytes(), &mut output).unwrap();
+
+        // `next` runs the first call to its return in one line.
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "> mov sp, 256 ; sp:0x0->0x100 ip:0x0->0x3 \n\
+             > call $+7 ; ax:0x0->0x1 ip:0x3->0x6 \n\
+             > call $+4 ; sp:0x100->0xfe ip:0x6->0xa \n\
+             > add ax, 1 ; ax:0x1->0x2 ip:0xa->0xd \n\
+             > breakpoint at 0x000a\n\
+             >    0006  e8 01 00          call $+4\n   \
+             0009  f4                hlt\n \
+             * 000a  83 c0 01          add ax, 1\n\
+             => 000d  c3                ret\n\
+             > ; halted, after 2 instructions\n\
+             > ip: 0x0006 (6)\n\
+             > ; breakpoint at 0x000a, after 1 instructions\n\
+             => 000a  83 c0 01          add ax, 1\n\
+             > 000fe: 09 00\n\
+             > \n"
+        );
+
+        // Stepping over the inner call of a recursion stops where that call
+        // returns, not where a deeper one returns to the same IP.
+        let contents = assemble(
+            "mov sp, 256\n\
+             mov cx, 3\n\
+             call count\n\
+             hlt\n\
+             count:\n\
+             sub cx, 1\n\
+             je done\n\
+             call count\n\
+             done:\n\
+             ret\n",
+        )
+        .unwrap();
+        let mut debugger = Debugger::new(Simulator::new(), &contents, &TraceOptions::default());
+        assert!(debugger
+            .command("s 5")
+            .unwrap()
+            .ends_with("je $+5 ; ip:0xd->0xf \n"));
+        assert_eq!(
+            debugger.command("n").unwrap(),
+            "call $-5 ; cx:0x2->0x0 ip:0xf->0x12 flags:->PZ \n"
+        );
+
+        // A loop that never ends comes back after the default step cap.
+        let contents = assemble("top:\njmp top\n").unwrap();
+        let mut debugger = Debugger::new(Simulator::new(), &contents, &TraceOptions::default());
+        assert_eq!(
+            debugger.command("c").unwrap(),
+            "; stopped after 100000 instructions\n\
+             => 0000  eb fe             jmp short $+0\n"
+        );
+
+        let mut debugger = Debugger::new(Simulator::new(), &contents, &TraceOptions::default());
+        assert_eq!(
+            debugger.command("set bl 0x1ff").unwrap(),
+            "bl only holds a byte, found 0x1ff\n"
+        );
+        assert_eq!(
+            debugger.command("set bh 0x12").unwrap(),
+            "bh: 0x0012 (18)\n"
+        );
+        assert_eq!(
+            debugger.command("w 0x100 0x34 0x12").unwrap(),
+            "00100: 34 12\n"
+        );
+        assert_eq!(
+            debugger
+                .simulator
+                .registers
+                .read(&EffectiveAddressCalculation::BX),
+            Some(0x1200)
+        );
+        assert_eq!(debugger.simulator.memory.slice(0x100, 2), [0x34, 0x12]);
+        assert_eq!(
+            debugger.command("jump").unwrap(),
+            "unknown command jump, try help\n"
+        );
+        assert!(debugger.command("quit").is_none());
+    }
+
+    #[test]
+    fn debugger_watchpoints_and_conditions() {
+        let contents = assemble(
+            "mov bx, 256\n\
+             mov cx, 4\n\
+             top:\n\
+             mov byte [bx], 65\n\
+             add bx, 1\n\
+             sub cx, 1\n\
+             jne top\n\
+             mov ax, [258]\n\
+             hlt\n",
+        )
+        .unwrap();
+
+        // The write into the watched range stops after the instruction.
+        let mut debugger = Debugger::new(Simulator::new(), &contents, &REFERENCE_TRACE);
+        let commands = [
+            ("watch 0x102 2", "write watchpoint on 00102..00104\n"),
+            (
+                "c",
+                "mov [bx], byte 65 ; \n\
+                 ; write watchpoint on 00102..00104, byte at 00102: 0x0->0x41\n\
+                 => 0009  83 c3 01          add bx, 1\n",
+            ),
+            ("unwatch 0x102", "deleted 1 watchpoints at 00102\n"),
+            ("rwatch ds:0x102 2", "read watchpoint on 00102..00104\n"),
+            (
+                "c",
+                "mov ax, [258] ; ax:0x0->0x4141 \n\
+                 ; read watchpoint on 00102..00104, word at 00102: 0x4141\n\
+                 => 0014  f4                hlt\n",
+            ),
+            ("watch 0xffffe 2", "write watchpoint on ffffe..100000\n"),
+            (
+                "watch 0xffffffffffffffff 2",
+                "expected an address and a length, found 0xffffffffffffffff 2\n",
+            ),
+            (
+                "watch 0xfffff 2",
+                "expected an address and a length, found 0xfffff 2\n",
+            ),
+            (
+                "watch 0x100000 1",
+                "expected an address and a length, found 0x100000 1\n",
+            ),
+            (
+                "x 0x100000 1",
+                "expected an address and a length, found 0x100000 1\n",
+            ),
+            (
+                "x 0 0xffffffffff",
+                "expected an address and a length, found 0 0xffffffffff\n",
+            ),
+            (
+                "x 0xffff0 0x11",
+                "ffff0: 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00\n00000: bb\n",
+            ),
+        ];
+        for (command, output) in commands {
+            assert_eq!(debugger.command(command).unwrap(), output, "{}", command);
+        }
+
+        // A condition without an IP stops once, where it starts to hold.
+        let mut debugger = Debugger::new(Simulator::new(), &contents, &REFERENCE_TRACE);
+        let commands = [
+            (
+                "b 6 if cx == 1 && [ds:bx - 1] == 0x41",
+                "breakpoint at 0x0006 if cx == 1 && [ds:bx - 1] == 0x41\n",
+            ),
+            ("b if bx == 0x102", "break if bx == 0x102\n"),
+            (
+                "c",
+                "add bx, 1 ; bx:0x101->0x102 flags:P-> \n\
+                 ; bx == 0x102 holds\n\
+                 => 000c  83 e9 01          sub cx, 1\n",
+            ),
+            (
+                "c",
+                "; breakpoint at 0x0006 if cx == 1 && [ds:bx - 1] == 0x41, after 6 instructions\n\
+                 => 0006  c6 07 41          mov [bx], byte 65\n",
+            ),
+            ("d if bx == 0x102", "deleted break if bx == 0x102\n"),
+            ("b if cx ==", "expression ends early at offset 5 in cx ==\n"),
+            ("b if cx = 1", "unexpected `=` at offset 3 in cx = 1\n"),
+            (
+                "b",
+                "breakpoint at 0x0006 if cx == 1 && [ds:bx - 1] == 0x41\n",
+            ),
+        ];
+        for (command, output) in commands {
+            assert_eq!(debugger.command(command).unwrap(), output, "{}", command);
+        }
+
+        let expression = Expression::parse("!zf || word [es:0x10 + di] >= 0x8000").unwrap();
+        let mut simulator = Simulator::new();
+        simulator.registers.flags.set(Flag::Zero, true);
+        assert!(!expression.holds(&simulator));
+        simulator.memory.write(0, 0x12, &Width::Word, 0x8001);
+        simulator
+            .registers
+            .write(&EffectiveAddressCalculation::DI, 2);
+        assert!(expression.holds(&simulator));
+    }
+
+    #[test]
+    fn dump_memory_and_render_images() {
+        let contents = assemble(
+            "mov word [256], 65535\n\
+             mov word [258], 255\n\
+             mov byte [262], 128\n",
+        )
+        .unwrap();
+
+        let mut simulator = Simulator::new();
+        simulator.run(&contents, "test", &REFERENCE_TRACE);
+
+        // The program is in memory as well as what it wrote.
+        assert_eq!(simulator.memory.slice(0, contents.len()), contents);
+        assert_eq!(
+            simulator.memory.slice(256, 8),
+            [0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x80, 0x00]
+        );
+
+        // Alpha is dropped.
+        let mut image = b"P6\n2 1\n255\n".to_vec();
+        image.extend_from_slice(&[0xff, 0xff, 0xff, 0x00, 0x00, 0x80]);
+        assert_eq!(simulator.memory.render_ppm(256, 2, 1), image);
+
+        assert_eq!(which_range("0x100:16"), Some((256, 16)));
+        assert_eq!(which_region("256:64x32"), Some((256, 64, 32)));
+        assert!(which_region("256:64").is_none());
+    }
+
+    #[test]
+    fn exec_follows_ip_until_hlt_or_off_the_image() {
+        let contents = assemble(
+            "mov cx, 3\n\
+             top:\n\
+             add ax, 10\n\
+             loop top\n\
+             hlt\n\
+             mov dx, 1\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            exec_bin(&contents, "test", &TraceOptions::default()),
+            "--- test execution ---\n\
+             mov cx, 3 ; cx:0x0->0x3 ip:0x0->0x3 \n\
+             add ax, 10 ; ax:0x0->0xa ip:0x3->0x6 flags:->P \n\
+             loop $-3 ; cx:0x3->0x2 ip:0x6->0x3 \n\
+             add ax, 10 ; ax:0xa->0x14 ip:0x3->0x6 flags:P->PA \n\
+             loop $-3 ; cx:0x2->0x1 ip:0x6->0x3 \n\
+             add ax, 10 ; ax:0x14->0x1e ip:0x3->0x6 flags:PA->P \n\
+             loop $-3 ; cx:0x1->0x0 ip:0x6->0x8 \n\
+             hlt ; ip:0x8->0x9 \n\
+             \n\
+             Final registers:\n      \
+             ax: 0x001e (30)\n      \
+             ip: 0x0009 (9)\n   \
+             flags: P\n\
+             \n"
+        );
+
+        // A jump past the end of the image ends the run.
+        let trace = exec_bin(&[0xeb, 0x10], "test", &TraceOptions::default());
+        assert!(trace.contains("jmp short $+18 ; ip:0x0->0x12 \n\nFinal registers:\n"));
+
+        // Listing 41 ends in a jnz pair that never falls through.
+        let contents = fs::read("vendor/listing_0041_add_sub_cmp_jnz").unwrap();
+        let options = TraceOptions {
+            ip: true,
+            max_steps: Some(200),
+            clocks: None,
+        };
+        let trace = exec_bin(&contents, "test", &options);
+        assert!(trace.contains("jne $+4 ; ip:0xc7->0xcb \njne $-4 ; ip:0xcb->0xc7 \n"));
+        assert!(trace.contains("; stopped after 200 instructions\n"));
+    }
+
+    #[test]
+    fn clocks_in_the_trace_and_the_listing() {
+        let contents = assemble(
+            "mov bx, 1000\n\
+             mov bp, 2000\n\
+             mov dx, [1000]\n\
+             mov cx, [bp]\n\
+             mov [bx + 1], cx\n\
+             add [bx + si + 4], cx\n\
+             mov cx, 2\n\
+             top:\n\
+             sub cx, 1\n\
+             jne top\n\
+             hlt\n",
+        )
+        .unwrap();
+
+        let options = TraceOptions {
+            ip: false,
+            max_steps: None,
+            clocks: Some(Cpu::I8086),
+        };
+        let trace = exec_bin(&contents, "test", &options);
+        assert!(trace.contains(
+            "mov bx, 1000 ; Clocks: +4 = 4 | bx:0x0->0x3e8 \n\
+             mov bp, 2000 ; Clocks: +4 = 8 | bp:0x0->0x7d0 \n\
+             mov dx, [1000] ; Clocks: +14 = 22 (8 + 6ea) | \n\
+             mov cx, [bp] ; Clocks: +17 = 39 (8 + 9ea) | \n\
+             mov [bx + 1], cx ; Clocks: +22 = 61 (9 + 9ea + 4p) | \n\
+             add [bx + si + 4], cx ; Clocks: +27 = 88 (16 + 11ea) | flags:->PZ \n"
+        ));
+        // Jumps cost more taken than not.
+        assert!(trace.contains(
+            "jne $-3 ; Clocks: +16 = 112 | \n\
+             sub cx, 1 ; Clocks: +4 = 116 | cx:0x1->0x0 flags:->PZ \n\
+             jne $-3 ; Clocks: +4 = 120 | \n\
+             hlt ; Clocks: +2 = 122 | \n"
+        ));
+
+        // The 8088 pays for every word transfer.
+        let options = TraceOptions {
+            clocks: Some(Cpu::I8088),
+            ..options
+        };
+        let trace = exec_bin(&contents, "test", &options);
+        assert!(trace.contains("mov dx, [1000] ; Clocks: +18 = 26 (8 + 6ea + 4p) | \n"));
+        assert!(trace.contains(
+            "add [bx + si + 4], cx ; Clocks: +35 = 104 (16 + 11ea + 8p) | flags:->PZ \n"
+        ));
+
+        let listing = process_bin_listing(&contents, &Nasm, false, Some(Cpu::I8086));
+        assert!(listing.starts_with(
+            "0000  bb e8 03          mov bx, 1000 ; Clocks: +4 = 4\n\
+             0003  bd d0 07          mov bp, 2000 ; Clocks: +4 = 8\n\
+             0006  8b 16 e8 03       mov dx, [1000] ; Clocks: +14 = 22 (8 + 6ea)\n"
+        ));
+        // Without registers, only direct addresses can be odd, and the jump
+        // counts as taken.
+        assert!(listing.contains("mov [bx + 1], cx ; Clocks: +18 = 57 (9 + 9ea)\n"));
+        assert!(listing.ends_with(
+            "jne $-3 ; Clocks: +16 = 108\n001b  f4                hlt ; Clocks: +2 = 110\n"
+        ));
+
+        // [bx + 0] with a zero disp8 pays for the displacement all the same.
+        let listing = process_bin_listing(
+            &[0x8b, 0x47, 0x00, 0x8b, 0x07],
+            &Nasm,
+            false,
+            Some(Cpu::I8086),
+        );
+        assert_eq!(
+            listing,
+            "0000  8b 47 00          db 0x8b, 0x47, 0x00 ; mov ax, [bx] ; Clocks: +17 = 17 (8 + 9ea)\n\
+             0003  8b 07             mov ax, [bx] ; Clocks: +13 = 30 (8 + 5ea)\n"
+        );
+
+        // The accumulator form has no address to calculate, and only the
+        // memory operand of a push pays for being odd, not the stack.
+        let contents = [0xa1, 0xe8, 0x03, 0xff, 0x36, 0xe9, 0x03];
+        assert_eq!(
+            process_bin_listing(&contents, &Nasm, false, Some(Cpu::I8086)),
+            "0000  a1 e8 03          mov ax, [1000] ; Clocks: +10 = 10\n\
+             0003  ff 36 e9 03       push word [1001] ; Clocks: +26 = 36 (16 + 6ea + 4p)\n"
+        );
+        assert!(
+            process_bin_listing(&contents, &Nasm, false, Some(Cpu::I8088))
+                .ends_with("push word [1001] ; Clocks: +30 = 44 (16 + 6ea + 8p)\n")
+        );
+
+        assert_eq!(which_cpu("8088"), Some(Cpu::I8088));
+        assert!(which_cpu("80286").is_none());
+    }
+
+    #[test]
+    fn exec_cmp_listing_0043_immediate_movs() {
+        const TXT_FILE_PATH: &str = "./vendor/listing_0043_immediate_movs.txt";
+        const BIN_FILE_PATH: &str = "./vendor/listing_0043_immediate_movs";
+
+        compare_exec(TXT_FILE_PATH, BIN_FILE_PATH)
+    }
+
+    #[test]
+    fn exec_cmp_listing_0044_register_movs() {
+        const TXT_FILE_PATH: &str = "./vendor/listing_0044_register_movs.txt";
+        const BIN_FILE_PATH: &str = "./vendor/listing_0044_register_movs";
+
+        compare_exec(TXT_FILE_PATH, BIN_FILE_PATH)
+    }
+
+    #[test]
+    fn exec_cmp_listing_0045_challenge_register_movs() {
+        const TXT_FILE_PATH: &str = "./vendor/listing_0045_challenge_register_movs.txt";
+        const BIN_FILE_PATH: &str = "./vendor/listing_0045_challenge_register_movs";
+
+        compare_exec(TXT_FILE_PATH, BIN_FILE_PATH)
+    }
 }
@@ -1,217 +1,574 @@
+use crate::clocks::{estimate_clocks, format_clocks, Cpu, Execution};
 use crate::decoder::constants::*;
+use crate::decoder::decoded_instruction::{DecodedInstruction, Prefixes};
+use crate::decoder::error::DecodeError;
 use crate::decoder::functions::*;
 use crate::decoder::utils::*;
+use crate::formatter::{Nasm, Syntax};
+use std::collections::{BTreeMap, BTreeSet};
+use std::{iter::Peekable, slice::Iter};
 
-pub(crate) fn process_bin(contents: &Vec<u8>) -> String {
-    let mut output: String = String::from("bits 16\n\n");
-    let mut contents_iterator = contents.iter().peekable();
-    // for instruction in contents.chunks(2) {
-    while let Some(instruction) = contents_iterator.next() {
-        let (opcode, opcode_instruction, _, _): (Op, Instruction, Option<u8>, Option<u8>) =
-            match instruction {
-                0b10000000..=0b10000011 => {
-                    let next_instruction = contents_iterator.peek().unwrap();
-                    let mode_field = (*next_instruction >> 6) & 0b11;
-                    let opcode_field = (*next_instruction >> 3) & 0b111;
-                    let rm_field = *next_instruction & 0b111;
-
-                    match opcode_field {
-                        0b000 => (
-                            Op::Add,
-                            Instruction::AddImmediateToRegisterMemory,
-                            Some(mode_field),
-                            Some(rm_field),
-                        ),
-                        0b101 => (
-                            Op::Sub,
-                            Instruction::SubImmediateFromRegisterMemory,
-                            Some(mode_field),
-                            Some(rm_field),
-                        ),
-                        0b111 => (
-                            Op::Cmp,
-                            Instruction::CmpImmediateWithRegisterMemory,
-                            Some(mode_field),
-                            Some(rm_field),
-                        ),
-                        _ => (Op::Invalid, Instruction::Invalid, None, None),
-                    }
-                }
-                0b01110100 => (Op::Je, Instruction::JumpOnEqual, None, None),
-                0b01111100 => (Op::Jl, Instruction::JumpOnLess, None, None),
-                0b01111110 => (Op::Jle, Instruction::JumpOnLessOrEqual, None, None),
-                0b01110010 => (Op::Jb, Instruction::JumpOnBelow, None, None),
-                0b01110110 => (Op::Jbe, Instruction::JumpOnBelowOrEqual, None, None),
-                0b01111010 => (Op::Jp, Instruction::JumpOnParity, None, None),
-                0b01110000 => (Op::Jo, Instruction::JumpOnOverflow, None, None),
-                0b01111000 => (Op::Js, Instruction::JumpOnSign, None, None),
-                0b01110101 => (Op::Jne, Instruction::JumpOnNotEqual, None, None),
-                0b01111101 => (Op::Jnl, Instruction::JumpOnNotLess, None, None),
-                0b01111111 => (Op::Jg, Instruction::JumpOnGreater, None, None),
-                0b01110011 => (Op::Jnb, Instruction::JumpOnNotBelow, None, None),
-                0b01110111 => (Op::Ja, Instruction::JumpOnAbove, None, None),
-                0b01111011 => (Op::Jnp, Instruction::JumpOnNotPar, None, None),
-                0b01110001 => (Op::Jno, Instruction::JumpOnNotOverflow, None, None),
-                0b01111001 => (Op::Jns, Instruction::JumpOnNotSign, None, None),
-                0b11100010 => (Op::Loop, Instruction::LoopCxTimes, None, None),
-                0b11100001 => (Op::Loopz, Instruction::LoopWhileZero, None, None),
-                0b11100000 => (Op::Loopnz, Instruction::LoopWhileNotZero, None, None),
-                0b11100011 => (Op::Jcxz, Instruction::JumpOnCxZero, None, None),
-                _ => {
-                    let (opcode, opcode_instruction) = which_instruction(instruction);
-
-                    (opcode, opcode_instruction, None, None)
-                }
-            };
-
-        match (opcode, opcode_instruction) {
-            (Op::Mov, Instruction::MovRegisterMemoryToFromRegister) => {
-                register_memory_to_from_register(
-                    Op::Mov,
-                    &mut contents_iterator,
-                    instruction,
-                    &mut output,
-                );
-            }
-            (Op::Mov, Instruction::MovImmediateToRegisterMemory) => {
-                immediate_to_register_memory(
-                    Op::Mov,
-                    &mut contents_iterator,
-                    instruction,
-                    &mut output,
-                );
-            }
-            (Op::Mov, Instruction::MovImmediateToRegister) => {
-                immediate_to_register(Op::Mov, &mut contents_iterator, instruction, &mut output);
-            }
-            (Op::Mov, Instruction::MovMemoryToAccumulator) => {
-                memory_to_accumulator(Op::Mov, &mut contents_iterator, &mut output);
-            }
-            (Op::Mov, Instruction::MovAccumulatorToMemory) => {
-                accumulator_to_memory(Op::Mov, &mut contents_iterator, &mut output);
-            }
-            (Op::Mov, Instruction::MovRegisterMemoryToSegmentRegister) => todo!(),
-            (Op::Mov, Instruction::MovSegmentRegisterToRegisterMemory) => todo!(),
-            (Op::Add, Instruction::AddRegisterMemoryWithRegisterToEither) => {
-                register_memory_to_from_register(
-                    Op::Add,
-                    &mut contents_iterator,
-                    instruction,
-                    &mut output,
-                );
-            }
-            (Op::Add, Instruction::AddImmediateToRegisterMemory) => {
-                immediate_to_register_memory(
-                    Op::Add,
-                    &mut contents_iterator,
-                    instruction,
-                    &mut output,
-                );
-            }
-            (Op::Add, Instruction::AddImmediateToAccumulator) => {
-                immediate_to_accumulator(Op::Add, &mut contents_iterator, instruction, &mut output);
-            }
-            (Op::Sub, Instruction::SubRegisterMemoryAndRegisterToEither) => {
-                register_memory_to_from_register(
-                    Op::Sub,
-                    &mut contents_iterator,
-                    instruction,
-                    &mut output,
-                );
-            }
-            (Op::Sub, Instruction::SubImmediateFromRegisterMemory) => {
-                immediate_to_register_memory(
-                    Op::Sub,
-                    &mut contents_iterator,
-                    instruction,
-                    &mut output,
-                );
-            }
-            (Op::Sub, Instruction::SubImmediateFromAccumulator) => {
-                immediate_to_accumulator(Op::Sub, &mut contents_iterator, instruction, &mut output);
-            }
-            (Op::Cmp, Instruction::CmpRegisterMemoryAndRegister) => {
-                register_memory_to_from_register(
-                    Op::Cmp,
-                    &mut contents_iterator,
-                    instruction,
-                    &mut output,
-                );
-            }
-            (Op::Cmp, Instruction::CmpImmediateWithRegisterMemory) => {
-                immediate_to_register_memory(
-                    Op::Cmp,
-                    &mut contents_iterator,
-                    instruction,
-                    &mut output,
-                );
-            }
-            (Op::Cmp, Instruction::CmpImmediateWithAccumulator) => {
-                immediate_to_accumulator(Op::Cmp, &mut contents_iterator, instruction, &mut output);
-            }
-            (Op::Je, Instruction::JumpOnEqual) => {
-                handle_jumps(Op::Je, &mut contents_iterator, &mut output);
-            }
-            (Op::Jl, Instruction::JumpOnLess) => {
-                handle_jumps(Op::Jl, &mut contents_iterator, &mut output);
-            }
-            (Op::Jle, Instruction::JumpOnLessOrEqual) => {
-                handle_jumps(Op::Jle, &mut contents_iterator, &mut output);
-            }
-            (Op::Jb, Instruction::JumpOnBelow) => {
-                handle_jumps(Op::Jb, &mut contents_iterator, &mut output);
-            }
-            (Op::Jbe, Instruction::JumpOnBelowOrEqual) => {
-                handle_jumps(Op::Jbe, &mut contents_iterator, &mut output);
-            }
-            (Op::Jp, Instruction::JumpOnParity) => {
-                handle_jumps(Op::Jp, &mut contents_iterator, &mut output);
-            }
-            (Op::Jo, Instruction::JumpOnOverflow) => {
-                handle_jumps(Op::Jo, &mut contents_iterator, &mut output);
-            }
-            (Op::Js, Instruction::JumpOnSign) => {
-                handle_jumps(Op::Js, &mut contents_iterator, &mut output);
-            }
-            (Op::Jne, Instruction::JumpOnNotEqual) => {
-                handle_jumps(Op::Jne, &mut contents_iterator, &mut output);
-            }
-            (Op::Jnl, Instruction::JumpOnNotLess) => {
-                handle_jumps(Op::Jnl, &mut contents_iterator, &mut output);
-            }
-            (Op::Jg, Instruction::JumpOnGreater) => {
-                handle_jumps(Op::Jg, &mut contents_iterator, &mut output);
-            }
-            (Op::Jnb, Instruction::JumpOnNotBelow) => {
-                handle_jumps(Op::Jnb, &mut contents_iterator, &mut output);
-            }
-            (Op::Ja, Instruction::JumpOnAbove) => {
-                handle_jumps(Op::Ja, &mut contents_iterator, &mut output);
-            }
-            (Op::Jnp, Instruction::JumpOnNotPar) => {
-                handle_jumps(Op::Jnp, &mut contents_iterator, &mut output);
+fn which_opcode(
+    instruction: &u8,
+    contents_iterator: &mut Peekable<Iter<'_, u8>>,
+    offset: &usize,
+) -> Result<(Op, Instruction), DecodeError> {
+    let opcode = match instruction {
+        0b10000000..=0b10000011 => {
+            let next_instruction = contents_iterator
+                .peek()
+                .ok_or(DecodeError::TruncatedInstruction { offset: *offset })?;
+            let opcode_field = (*next_instruction >> 3) & 0b111;
+
+            match opcode_field {
+                0b000 => (Op::Add, Instruction::AddImmediateToRegisterMemory),
+                0b001 => (Op::Or, Instruction::OrImmediateToRegisterMemory),
+                0b010 => (Op::Adc, Instruction::AdcImmediateToRegisterMemory),
+                0b011 => (Op::Sbb, Instruction::SbbImmediateFromRegisterMemory),
+                0b100 => (Op::And, Instruction::AndImmediateToRegisterMemory),
+                0b101 => (Op::Sub, Instruction::SubImmediateFromRegisterMemory),
+                0b110 => (Op::Xor, Instruction::XorImmediateToRegisterMemory),
+                0b111 => (Op::Cmp, Instruction::CmpImmediateWithRegisterMemory),
+                _ => (Op::Invalid, Instruction::Invalid),
             }
-            (Op::Jno, Instruction::JumpOnNotOverflow) => {
-                handle_jumps(Op::Jno, &mut contents_iterator, &mut output);
+        }
+        0b11010000..=0b11010011 => {
+            let next_instruction = contents_iterator
+                .peek()
+                .ok_or(DecodeError::TruncatedInstruction { offset: *offset })?;
+            let opcode_field = (*next_instruction >> 3) & 0b111;
+
+            match opcode_field {
+                0b000 => (Op::Rol, Instruction::RotateLeft),
+                0b001 => (Op::Ror, Instruction::RotateRight),
+                0b010 => (Op::Rcl, Instruction::RotateThroughCarryLeft),
+                0b011 => (Op::Rcr, Instruction::RotateThroughCarryRight),
+                0b100 => (Op::Shl, Instruction::ShiftLogicalLeft),
+                0b101 => (Op::Shr, Instruction::ShiftLogicalRight),
+                0b111 => (Op::Sar, Instruction::ShiftArithmeticRight),
+                _ => (Op::Invalid, Instruction::Invalid),
             }
-            (Op::Jns, Instruction::JumpOnNotSign) => {
-                handle_jumps(Op::Jns, &mut contents_iterator, &mut output);
+        }
+        0b11110110..=0b11110111 => {
+            let next_instruction = contents_iterator
+                .peek()
+                .ok_or(DecodeError::TruncatedInstruction { offset: *offset })?;
+            let opcode_field = (*next_instruction >> 3) & 0b111;
+
+            match opcode_field {
+                0b000 => (Op::Test, Instruction::TestImmediateDataAndRegisterMemory),
+                0b010 => (Op::Not, Instruction::NotInvert),
+                0b011 => (Op::Neg, Instruction::NegChangeSign),
+                0b100 => (Op::Mul, Instruction::MultiplyUnsigned),
+                0b101 => (Op::Imul, Instruction::IntegerMultiplySigned),
+                0b110 => (Op::Div, Instruction::DivideUnsigned),
+                0b111 => (Op::Idiv, Instruction::IntegerDivideSigned),
+                _ => (Op::Invalid, Instruction::Invalid),
             }
-            (Op::Loop, Instruction::LoopCxTimes) => {
-                handle_loops(Op::Loop, &mut contents_iterator, &mut output);
+        }
+        0b11111110..=0b11111111 => {
+            let next_instruction = contents_iterator
+                .peek()
+                .ok_or(DecodeError::TruncatedInstruction { offset: *offset })?;
+            let opcode_field = (*next_instruction >> 3) & 0b111;
+
+            match (instruction, opcode_field) {
+                (_, 0b000) => (Op::Inc, Instruction::IncRegisterMemory),
+                (_, 0b001) => (Op::Dec, Instruction::DecRegisterMemory),
+                (0b11111111, 0b010) => (Op::Call, Instruction::CallIndirectWithinSegment),
+                (0b11111111, 0b011) => (Op::Call, Instruction::CallIndirectIntersegment),
+                (0b11111111, 0b100) => (Op::Jmp, Instruction::JmpIndirectWithinSegment),
+                (0b11111111, 0b101) => (Op::Jmp, Instruction::JmpIndirectIntersegment),
+                (0b11111111, 0b110) => (Op::Push, Instruction::PushRegisterMemory),
+                _ => (Op::Invalid, Instruction::Invalid),
             }
-            (Op::Loopz, Instruction::LoopWhileZero) => {
-                handle_loops(Op::Loopz, &mut contents_iterator, &mut output);
+        }
+        0b11110011 => {
+            // REP and REPE share an encoding, the mnemonic depends on
+            // whether the string instruction compares or not. Other
+            // prefixes can come in between.
+            match contents_iterator.clone().find(|byte| !is_prefix(byte)) {
+                Some(0b10100110..=0b10100111) | Some(0b10101110..=0b10101111) => {
+                    (Op::Repe, Instruction::Repeat)
+                }
+                _ => (Op::Rep, Instruction::Repeat),
             }
-            (Op::Loopnz, Instruction::LoopWhileNotZero) => {
-                handle_loops(Op::Loopnz, &mut contents_iterator, &mut output);
+        }
+        0b11110010 => (Op::Repne, Instruction::Repeat),
+        0b01110100 => (Op::Je, Instruction::JumpOnEqual),
+        0b01111100 => (Op::Jl, Instruction::JumpOnLess),
+        0b01111110 => (Op::Jle, Instruction::JumpOnLessOrEqual),
+        0b01110010 => (Op::Jb, Instruction::JumpOnBelow),
+        0b01110110 => (Op::Jbe, Instruction::JumpOnBelowOrEqual),
+        0b01111010 => (Op::Jp, Instruction::JumpOnParity),
+        0b01110000 => (Op::Jo, Instruction::JumpOnOverflow),
+        0b01111000 => (Op::Js, Instruction::JumpOnSign),
+        0b01110101 => (Op::Jne, Instruction::JumpOnNotEqual),
+        0b01111101 => (Op::Jnl, Instruction::JumpOnNotLess),
+        0b01111111 => (Op::Jg, Instruction::JumpOnGreater),
+        0b01110011 => (Op::Jnb, Instruction::JumpOnNotBelow),
+        0b01110111 => (Op::Ja, Instruction::JumpOnAbove),
+        0b01111011 => (Op::Jnp, Instruction::JumpOnNotPar),
+        0b01110001 => (Op::Jno, Instruction::JumpOnNotOverflow),
+        0b01111001 => (Op::Jns, Instruction::JumpOnNotSign),
+        0b11100010 => (Op::Loop, Instruction::LoopCxTimes),
+        0b11100001 => (Op::Loopz, Instruction::LoopWhileZero),
+        0b11100000 => (Op::Loopnz, Instruction::LoopWhileNotZero),
+        0b11100011 => (Op::Jcxz, Instruction::JumpOnCxZero),
+        _ => which_instruction(instruction),
+    };
+
+    Ok(opcode)
+}
+
+pub(crate) fn decode_instruction(
+    contents_iterator: &mut Peekable<Iter<'_, u8>>,
+    offset: usize,
+) -> Result<DecodedInstruction, DecodeError> {
+    let remaining = contents_iterator.len();
+    // Prefix bytes are decoded on their own and carried into the
+    // instruction that follows them.
+    let mut prefixes = Prefixes::default();
+
+    let (instruction, opcode, opcode_instruction) = loop {
+        let instruction = contents_iterator
+            .next()
+            .ok_or(DecodeError::TruncatedInstruction { offset })?;
+
+        match which_opcode(instruction, contents_iterator, &offset)? {
+            (_, Instruction::BusLockPrefix) => {
+                prefixes.lock = true;
+                prefixes.bytes.push(*instruction);
+            }
+            (_, Instruction::SegmentOverridePrefix) => {
+                let segment_register_field = (instruction >> 3) & 0b11;
+                prefixes.segment = Some(which_segment_register(&segment_register_field));
+                prefixes.bytes.push(*instruction);
+            }
+            (opcode, Instruction::Repeat) => {
+                prefixes.repeat = Some(opcode);
+                prefixes.bytes.push(*instruction);
+            }
+            (Op::Invalid, _) => {
+                return Err(DecodeError::UndefinedOpcode {
+                    offset,
+                    opcode: *instruction,
+                })
+            }
+            (opcode, opcode_instruction) => break (instruction, opcode, opcode_instruction),
+        }
+    };
+
+    let mut decoded = DecodedInstruction::new(opcode, opcode_instruction, prefixes, offset);
+
+    match opcode_instruction {
+        Instruction::MovRegisterMemoryToFromRegister
+        | Instruction::XchgRegisterMemoryWithRegister
+        | Instruction::AddRegisterMemoryWithRegisterToEither
+        | Instruction::AdcRegisterMemoryWithRegisterToEither
+        | Instruction::SubRegisterMemoryAndRegisterToEither
+        | Instruction::SbbRegisterMemoryAndRegisterToEither
+        | Instruction::CmpRegisterMemoryAndRegister
+        | Instruction::AndRegisterMemoryWithRegisterToEither
+        | Instruction::TestRegisterMemoryAndRegister
+        | Instruction::OrRegisterMemoryAndRegisterToEither
+        | Instruction::XorRegisterMemoryAndRegisterToEither => {
+            register_memory_to_from_register(contents_iterator, instruction, &mut decoded)?;
+        }
+        Instruction::MovRegisterMemoryToSegmentRegister
+        | Instruction::MovSegmentRegisterToRegisterMemory => {
+            segment_register_to_from_register_memory(contents_iterator, instruction, &mut decoded)?;
+        }
+        Instruction::LoadEffectiveAddressToRegister
+        | Instruction::LoadPointerToDs
+        | Instruction::LoadPointerToEs => {
+            register_memory_to_register(contents_iterator, &mut decoded)?;
+        }
+        Instruction::MovImmediateToRegisterMemory
+        | Instruction::AddImmediateToRegisterMemory
+        | Instruction::AdcImmediateToRegisterMemory
+        | Instruction::SubImmediateFromRegisterMemory
+        | Instruction::SbbImmediateFromRegisterMemory
+        | Instruction::CmpImmediateWithRegisterMemory
+        | Instruction::AndImmediateToRegisterMemory
+        | Instruction::TestImmediateDataAndRegisterMemory
+        | Instruction::OrImmediateToRegisterMemory
+        | Instruction::XorImmediateToRegisterMemory => {
+            immediate_to_register_memory(contents_iterator, instruction, &mut decoded)?;
+        }
+        Instruction::MovImmediateToRegister => {
+            immediate_to_register(contents_iterator, instruction, &mut decoded)?;
+        }
+        Instruction::MovMemoryToAccumulator => {
+            memory_to_accumulator(contents_iterator, instruction, &mut decoded)?;
+        }
+        Instruction::MovAccumulatorToMemory => {
+            accumulator_to_memory(contents_iterator, instruction, &mut decoded)?;
+        }
+        Instruction::AddImmediateToAccumulator
+        | Instruction::AdcImmediateToAccumulator
+        | Instruction::SubImmediateFromAccumulator
+        | Instruction::SbbImmediateFromAccumulator
+        | Instruction::CmpImmediateWithAccumulator
+        | Instruction::AndImmediateToAccumulator
+        | Instruction::TestImmediateDataAndAccumulator
+        | Instruction::OrImmediateToAccumulator
+        | Instruction::XorImmediateToAccumulator => {
+            immediate_to_accumulator(contents_iterator, instruction, &mut decoded)?;
+        }
+        Instruction::PushRegisterMemory
+        | Instruction::PopRegisterMemory
+        | Instruction::IncRegisterMemory
+        | Instruction::DecRegisterMemory
+        | Instruction::NegChangeSign
+        | Instruction::MultiplyUnsigned
+        | Instruction::IntegerMultiplySigned
+        | Instruction::DivideUnsigned
+        | Instruction::IntegerDivideSigned
+        | Instruction::NotInvert => {
+            register_memory(contents_iterator, instruction, &mut decoded)?;
+        }
+        Instruction::PushRegister
+        | Instruction::PopRegister
+        | Instruction::IncRegister
+        | Instruction::DecRegister => {
+            register(instruction, &mut decoded)?;
+        }
+        Instruction::XchgRegisterWithAccumulator => {
+            register_with_accumulator(instruction, &mut decoded)?;
+        }
+        Instruction::PushSegmentRegister | Instruction::PopSegmentRegister => {
+            segment_register(instruction, &mut decoded)?;
+        }
+        Instruction::InFixedPort | Instruction::OutFixedPort => {
+            fixed_port(contents_iterator, instruction, &mut decoded)?;
+        }
+        Instruction::InVariablePort | Instruction::OutVariablePort => {
+            variable_port(instruction, &mut decoded)?;
+        }
+        Instruction::ShiftLogicalLeft
+        | Instruction::ShiftLogicalRight
+        | Instruction::ShiftArithmeticRight
+        | Instruction::RotateLeft
+        | Instruction::RotateRight
+        | Instruction::RotateThroughCarryLeft
+        | Instruction::RotateThroughCarryRight => {
+            shift_rotate(contents_iterator, instruction, &mut decoded)?;
+        }
+        Instruction::AsciiAdjustForMultiply | Instruction::AsciiAdjustForDivide => {
+            ascii_adjust(contents_iterator, &mut decoded)?;
+        }
+        Instruction::MoveByteOrWord
+        | Instruction::CompareByteOrWord
+        | Instruction::ScanByteOrWord
+        | Instruction::LoadByteOrWordToAlAx
+        | Instruction::StoreByteOrWordFromAlAx => {
+            string_manipulation(instruction, &mut decoded)?;
+        }
+        Instruction::CallDirectWithinSegment
+        | Instruction::JmpDirectWithinSegment
+        | Instruction::JmpDirectWithinSegmentShort => {
+            direct_within_segment(contents_iterator, instruction, &mut decoded)?;
+        }
+        Instruction::CallDirectIntersegment | Instruction::JmpDirectIntersegment => {
+            direct_intersegment(contents_iterator, &mut decoded)?;
+        }
+        Instruction::CallIndirectWithinSegment
+        | Instruction::CallIndirectIntersegment
+        | Instruction::JmpIndirectWithinSegment
+        | Instruction::JmpIndirectIntersegment => {
+            indirect(contents_iterator, &mut decoded)?;
+        }
+        Instruction::RetWithinSegmentAddingImmediateToSp
+        | Instruction::RetIntersegmentAddingImmediateToSp => {
+            return_adding_immediate(contents_iterator, &mut decoded)?;
+        }
+        Instruction::InterruptTypeSpecified => {
+            interrupt(contents_iterator, &mut decoded)?;
+        }
+        Instruction::EscapeToExternalDevice => {
+            escape(contents_iterator, instruction, &mut decoded)?;
+        }
+        Instruction::JumpOnEqual
+        | Instruction::JumpOnLess
+        | Instruction::JumpOnLessOrEqual
+        | Instruction::JumpOnBelow
+        | Instruction::JumpOnBelowOrEqual
+        | Instruction::JumpOnParity
+        | Instruction::JumpOnOverflow
+        | Instruction::JumpOnSign
+        | Instruction::JumpOnNotEqual
+        | Instruction::JumpOnNotLess
+        | Instruction::JumpOnGreater
+        | Instruction::JumpOnNotBelow
+        | Instruction::JumpOnAbove
+        | Instruction::JumpOnNotPar
+        | Instruction::JumpOnNotOverflow
+        | Instruction::JumpOnNotSign => {
+            handle_jumps(contents_iterator, &mut decoded)?;
+        }
+        Instruction::LoopCxTimes
+        | Instruction::LoopWhileZero
+        | Instruction::LoopWhileNotZero
+        | Instruction::JumpOnCxZero => {
+            handle_loops(contents_iterator, &mut decoded)?;
+        }
+        _ => standalone(&mut decoded)?,
+    }
+
+    decoded.length = remaining - contents_iterator.len();
+
+    Ok(decoded)
+}
+
+/// Decodes the instruction that starts at `offset` in `contents`.
+pub fn decode(contents: &[u8], offset: usize) -> Result<DecodedInstruction, DecodeError> {
+    let mut contents_iterator = contents.get(offset..).unwrap_or_default().iter().peekable();
+
+    decode_instruction(&mut contents_iterator, offset)
+}
+
+/// Walks `contents` from its first byte, one decoded instruction at a time.
+pub struct Decoder<'a> {
+    contents: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(contents: &'a [u8]) -> Self {
+        Decoder {
+            contents,
+            offset: 0,
+        }
+    }
+}
+
+impl Iterator for Decoder<'_> {
+    type Item = Result<DecodedInstruction, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.contents.len() {
+            return None;
+        }
+
+        match decode(self.contents, self.offset) {
+            Ok(decoded) => {
+                self.offset += decoded.length;
+                Some(Ok(decoded))
             }
-            (Op::Jcxz, Instruction::JumpOnCxZero) => {
-                handle_loops(Op::Jcxz, &mut contents_iterator, &mut output);
+            Err(error) => {
+                // Only the first byte is known to be bad, pick up again right after it.
+                self.offset = error.offset() + 1;
+                Some(Err(error))
             }
-            (_, _) => (),
         }
     }
+}
+
+/// Decodes every instruction in `contents`.
+pub fn decode_bin(contents: &[u8]) -> Result<Vec<DecodedInstruction>, DecodeError> {
+    Decoder::new(contents).collect()
+}
+
+/// Decodes `contents` and formats it as a NASM listing. Bytes that don't
+/// decode are kept as `db` lines.
+pub fn process_bin(contents: &[u8]) -> String {
+    process_bin_as(contents, &Nasm)
+}
+
+/// Decodes `contents` and formats it as a listing in `syntax`.
+pub fn process_bin_as(contents: &[u8], syntax: &dyn Syntax) -> String {
+    let mut output: String = format!("{}\n\n", syntax.header());
+
+    for decoded in Decoder::new(contents) {
+        let line = match decoded {
+            Ok(decoded) => syntax.format_instruction(&decoded, None),
+            Err(error) => syntax.format_undecodable(contents, &error),
+        };
+
+        if !line.is_empty() {
+            output.push_str(format!("{}\n", line).as_str());
+        }
+    }
+
+    output
+}
+
+// Names every jump, loop and call target that starts a line, or sits at the
+// very end of `contents`, `label_N` in order of offset.
+fn which_labels(
+    contents: &[u8],
+    lines: &[Result<DecodedInstruction, DecodeError>],
+) -> BTreeMap<usize, String> {
+    // Every offset a line starts at. A label can also sit at the very end.
+    let mut boundaries: BTreeSet<usize> = lines
+        .iter()
+        .map(|line| match line {
+            Ok(decoded) => decoded.offset,
+            Err(error) => error.offset(),
+        })
+        .collect();
+    boundaries.insert(contents.len());
+
+    let targets: BTreeSet<usize> = lines
+        .iter()
+        .flatten()
+        .filter_map(|decoded| usize::try_from(decoded.target()?).ok())
+        .filter(|target| boundaries.contains(target))
+        .collect();
+
+    targets
+        .into_iter()
+        .enumerate()
+        .map(|(index, target)| (target, format!("label_{}", index)))
+        .collect()
+}
+
+/// Decodes `contents` in two passes and formats it as a NASM listing, with a
+/// `label_N:` line at every jump, loop and call target. Targets outside the
+/// buffer or inside an instruction keep the `$` form and get a comment.
+pub fn process_bin_with_labels(contents: &[u8]) -> String {
+    process_bin_with_labels_as(contents, &Nasm)
+}
+
+/// Decodes `contents` in two passes and formats it as a labelled listing in
+/// `syntax`.
+pub fn process_bin_with_labels_as(contents: &[u8], syntax: &dyn Syntax) -> String {
+    let lines: Vec<Result<DecodedInstruction, DecodeError>> = Decoder::new(contents).collect();
+    let labels = which_labels(contents, &lines);
+
+    let mut output: String = format!("{}\n\n", syntax.header());
+
+    for line in lines {
+        let (offset, line) = match line {
+            Ok(decoded) => (
+                decoded.offset,
+                format_with_label(&decoded, &labels, contents, syntax),
+            ),
+            Err(error) => (error.offset(), syntax.format_undecodable(contents, &error)),
+        };
+
+        if let Some(label) = labels.get(&offset) {
+            output.push_str(format!("{}:\n", label).as_str());
+        }
+
+        if !line.is_empty() {
+            output.push_str(format!("{}\n", line).as_str());
+        }
+    }
+
+    if let Some(label) = labels.get(&contents.len()) {
+        output.push_str(format!("{}:\n", label).as_str());
+    }
+
+    output
+}
+
+fn format_with_label(
+    decoded: &DecodedInstruction,
+    labels: &BTreeMap<usize, String>,
+    contents: &[u8],
+    syntax: &dyn Syntax,
+) -> String {
+    let Some(target) = decoded.target() else {
+        return syntax.format_instruction(decoded, None);
+    };
+
+    match usize::try_from(target)
+        .ok()
+        .and_then(|target| labels.get(&target))
+    {
+        Some(label) => syntax.format_instruction(decoded, Some(label)),
+        None if target < 0 || target as usize > contents.len() => format!(
+            "{} {} target {} is outside the buffer",
+            syntax.format_instruction(decoded, None),
+            syntax.comment(),
+            target
+        ),
+        None => format!(
+            "{} {} target {} is inside an instruction",
+            syntax.format_instruction(decoded, None),
+            syntax.comment(),
+            target
+        ),
+    }
+}
+
+/// Decodes `contents` into a listing like `objdump -d` prints: every line
+/// starts with its offset in hex and the bytes it was decoded from. With
+/// `labels`, jump targets get `label_N:` lines as in the labelled listing.
+/// With `cpu`, every instruction ends in a comment with its clocks and the
+/// running total, counting conditional jumps as taken.
+pub fn process_bin_listing(
+    contents: &[u8],
+    syntax: &dyn Syntax,
+    labels: bool,
+    cpu: Option<Cpu>,
+) -> String {
+    let lines: Vec<Result<DecodedInstruction, DecodeError>> = Decoder::new(contents).collect();
+    let labels = labels.then(|| which_labels(contents, &lines));
+
+    let mut output = String::new();
+    let mut total: u32 = 0;
+
+    for line in lines {
+        let clocks = match (&line, cpu) {
+            (Ok(decoded), Some(cpu)) => {
+                let execution = Execution {
+                    address: None,
+                    taken: true,
+                };
+                let bytes = &contents[decoded.offset..][..decoded.length];
+                let clocks = estimate_clocks(decoded, bytes, &cpu, &execution);
+                total += clocks.map(|clocks| clocks.total()).unwrap_or_default();
+                Some(format_clocks(clocks.as_ref(), total))
+            }
+            _ => None,
+        };
+
+        let (offset, length, mut text) = match (line, &labels) {
+            (Ok(decoded), Some(labels)) => (
+                decoded.offset,
+                decoded.length,
+                format_with_label(&decoded, labels, contents, syntax),
+            ),
+            (Ok(decoded), None) => (
+                decoded.offset,
+                decoded.length,
+                syntax.format_instruction(&decoded, None),
+            ),
+            (Err(error), _) => (
+                error.offset(),
+                1,
+                syntax.format_undecodable(contents, &error),
+            ),
+        };
+
+        if let Some(clocks) = clocks {
+            text = format!("{} {} {}", text, syntax.comment(), clocks);
+        }
+
+        if let Some(label) = labels.as_ref().and_then(|labels| labels.get(&offset)) {
+            output.push_str(format!("{}:\n", label).as_str());
+        }
+
+        let bytes: Vec<String> = contents[offset..offset + length]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        // Room for six bytes keeps the mnemonics in one column for all but
+        // prefixed instructions.
+        let line = format!("{:04x}  {:<18}{}", offset, bytes.join(" "), text);
+        output.push_str(format!("{}\n", line.trim_end()).as_str());
+    }
+
+    if let Some(label) = labels
+        .as_ref()
+        .and_then(|labels| labels.get(&contents.len()))
+    {
+        output.push_str(format!("{}:\n", label).as_str());
+    }
 
     output
 }
@@ -0,0 +1,527 @@
+pub(crate) mod arithmetic;
+pub mod debugger;
+pub mod error;
+pub mod expression;
+pub mod flags;
+pub mod memory;
+pub mod registers;
+
+use crate::clocks::{estimate_clocks, format_clocks, Cpu, Execution};
+use crate::decoder::constants::{EffectiveAddressCalculation, Instruction, Op, SegmentRegister};
+use crate::decoder::decoded_instruction::{DecodedInstruction, MemoryAddress, Operand, Width};
+use crate::formatter::nasm::format_trace_instruction;
+use crate::processor::decode;
+use arithmetic::arithmetic;
+use error::SimulateError;
+use flags::Flag;
+use memory::{Memory, MemoryAccess};
+use registers::Registers;
+use std::ops::Range;
+
+/// Executes decoded instructions against an 8086 register file and 1 MiB of
+/// memory.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Simulator {
+    pub registers: Registers,
+    pub memory: Memory,
+    // Set by `hlt`.
+    pub halted: bool,
+    // The running total of estimated clocks when tracing with them.
+    pub clocks: u32,
+    // The memory the last instruction read and wrote, in order.
+    pub accesses: Vec<MemoryAccess>,
+}
+
+/// What a trace shows and how long it runs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceOptions {
+    /// Trace IP and list it with the final registers.
+    pub ip: bool,
+    /// Stop after this many instructions, for programs that never halt.
+    pub max_steps: Option<usize>,
+    /// Estimate every instruction's clocks on this processor and keep a
+    /// running total.
+    pub clocks: Option<Cpu>,
+}
+
+impl Default for TraceOptions {
+    fn default() -> Self {
+        TraceOptions {
+            ip: true,
+            max_steps: None,
+            clocks: None,
+        }
+    }
+}
+
+fn unsupported(decoded: &DecodedInstruction) -> SimulateError {
+    SimulateError::Unsupported {
+        offset: decoded.offset,
+        line: format_trace_instruction(decoded),
+    }
+}
+
+impl Simulator {
+    pub fn new() -> Self {
+        Simulator::default()
+    }
+
+    /// The physical address of CS:IP.
+    pub fn instruction_address(&self) -> usize {
+        Memory::physical(
+            self.registers.read_segment(&SegmentRegister::CS),
+            self.registers.ip,
+        )
+    }
+
+    /// Decodes the instruction at CS:IP without executing it.
+    pub fn fetch(&self) -> Result<DecodedInstruction, SimulateError> {
+        Ok(decode(self.memory.as_slice(), self.instruction_address())?)
+    }
+
+    /// Fetches and executes the instruction at CS:IP.
+    pub fn step(&mut self) -> Result<DecodedInstruction, SimulateError> {
+        let decoded = self.fetch()?;
+        self.execute(&decoded)?;
+        Ok(decoded)
+    }
+
+    // Steps like `step` and also returns what the clock estimate needs: the
+    // offset of the memory operand before the instruction changes any
+    // registers, and whether it jumped.
+    fn step_timed(&mut self) -> Result<(DecodedInstruction, Execution), SimulateError> {
+        let decoded = self.fetch()?;
+        let next = self.registers.ip.wrapping_add(decoded.length as u16);
+
+        let address = decoded.operands.iter().find_map(|operand| match operand {
+            Operand::Memory(address) => Some(self.effective_address(address).1),
+            _ => None,
+        });
+
+        self.execute(&decoded)?;
+
+        let execution = Execution {
+            address,
+            taken: self.registers.ip != next,
+        };
+
+        Ok((decoded, execution))
+    }
+
+    /// Executes one decoded instruction and moves IP past it, or to where it
+    /// jumps. Anything outside the supported subset leaves the registers as
+    /// they were.
+    pub fn execute(&mut self, decoded: &DecodedInstruction) -> Result<(), SimulateError> {
+        self.accesses.clear();
+        let next = self.registers.ip.wrapping_add(decoded.length as u16);
+
+        let ip = match (decoded.op, decoded.operands.as_slice()) {
+            (Op::Mov, [destination, source]) => {
+                let value = self.read_operand(decoded, source)?;
+                self.write_operand(decoded, destination, value)?;
+                next
+            }
+            (
+                Op::Add
+                | Op::Adc
+                | Op::Sub
+                | Op::Sbb
+                | Op::Cmp
+                | Op::And
+                | Op::Test
+                | Op::Or
+                | Op::Xor,
+                [destination, source],
+            ) => {
+                let width = decoded.width.ok_or_else(|| unsupported(decoded))?;
+                let (result, flags) = arithmetic(
+                    &decoded.op,
+                    &width,
+                    self.read_operand(decoded, destination)?,
+                    self.read_operand(decoded, source)?,
+                    &self.registers.flags,
+                )
+                .ok_or_else(|| unsupported(decoded))?;
+
+                // Cmp and test only keep the flags.
+                if !matches!(decoded.op, Op::Cmp | Op::Test) {
+                    self.write_operand(decoded, destination, result)?;
+                }
+                self.registers.flags = flags;
+                next
+            }
+            (Op::Call, [Operand::Relative(displacement)]) => {
+                self.push(next);
+                next.wrapping_add(*displacement as u16)
+            }
+            (Op::Call, [target @ (Operand::Register(_) | Operand::Memory(_))]) => {
+                let target = self.read_word(decoded, target)?;
+                self.push(next);
+                target
+            }
+            (Op::Ret, []) => self.pop(),
+            (Op::Ret, [Operand::Immediate(data)]) => {
+                let ip = self.pop();
+                let sp = self.read_register(EffectiveAddressCalculation::SP);
+                self.registers.write(
+                    &EffectiveAddressCalculation::SP,
+                    sp.wrapping_add(*data as u16),
+                );
+                ip
+            }
+            (_, [Operand::Relative(displacement)]) => {
+                let jump = match decoded.instruction {
+                    Instruction::JmpDirectWithinSegment
+                    | Instruction::JmpDirectWithinSegmentShort => true,
+                    _ => self.which_condition(decoded)?,
+                };
+
+                if jump {
+                    next.wrapping_add(*displacement as u16)
+                } else {
+                    next
+                }
+            }
+            (Op::Hlt, []) => {
+                self.halted = true;
+                next
+            }
+            _ => return Err(unsupported(decoded)),
+        };
+
+        self.registers.ip = ip;
+        Ok(())
+    }
+
+    // Whether a conditional jump or loop is taken. Loops count CX down
+    // first.
+    fn which_condition(&mut self, decoded: &DecodedInstruction) -> Result<bool, SimulateError> {
+        let flags = self.registers.flags;
+        let carry = flags.get(Flag::Carry);
+        let parity = flags.get(Flag::Parity);
+        let zero = flags.get(Flag::Zero);
+        let sign = flags.get(Flag::Sign);
+        let overflow = flags.get(Flag::Overflow);
+
+        let cx = self
+            .registers
+            .read(&EffectiveAddressCalculation::CX)
+            .unwrap_or_default();
+
+        let condition = match decoded.op {
+            Op::Je => zero,
+            Op::Jne => !zero,
+            Op::Jl => sign != overflow,
+            Op::Jnl => sign == overflow,
+            Op::Jle => zero || sign != overflow,
+            Op::Jg => !zero && sign == overflow,
+            Op::Jb => carry,
+            Op::Jnb => !carry,
+            Op::Jbe => carry || zero,
+            Op::Ja => !carry && !zero,
+            Op::Jp => parity,
+            Op::Jnp => !parity,
+            Op::Jo => overflow,
+            Op::Jno => !overflow,
+            Op::Js => sign,
+            Op::Jns => !sign,
+            Op::Jcxz => cx == 0,
+            Op::Loop | Op::Loopz | Op::Loopnz => {
+                let cx = cx.wrapping_sub(1);
+                self.registers.write(&EffectiveAddressCalculation::CX, cx);
+
+                match decoded.op {
+                    Op::Loopz => cx != 0 && zero,
+                    Op::Loopnz => cx != 0 && !zero,
+                    _ => cx != 0,
+                }
+            }
+            _ => return Err(unsupported(decoded)),
+        };
+
+        Ok(condition)
+    }
+
+    fn read_register(&self, register: EffectiveAddressCalculation) -> u16 {
+        self.registers.read(&register).unwrap_or_default()
+    }
+
+    // Reads memory and notes the access.
+    fn read_memory(&mut self, segment: u16, offset: u16, width: Width) -> u16 {
+        let value = self.memory.read(segment, offset, &width);
+        self.accesses.push(MemoryAccess {
+            write: false,
+            address: Memory::physical(segment, offset),
+            width,
+            old: value,
+            new: value,
+        });
+
+        value
+    }
+
+    // Writes memory and notes the access with the value it replaced.
+    fn write_memory(&mut self, segment: u16, offset: u16, width: Width, value: u16) {
+        let old = self.memory.read(segment, offset, &width);
+        self.memory.write(segment, offset, &width, value);
+        self.accesses.push(MemoryAccess {
+            write: true,
+            address: Memory::physical(segment, offset),
+            width,
+            old,
+            new: self.memory.read(segment, offset, &width),
+        });
+    }
+
+    // Pushes a word onto SS:SP.
+    fn push(&mut self, value: u16) {
+        let sp = self
+            .read_register(EffectiveAddressCalculation::SP)
+            .wrapping_sub(2);
+        self.registers.write(&EffectiveAddressCalculation::SP, sp);
+        let ss = self.registers.read_segment(&SegmentRegister::SS);
+        self.write_memory(ss, sp, Width::Word, value);
+    }
+
+    // Pops a word off SS:SP.
+    fn pop(&mut self) -> u16 {
+        let sp = self.read_register(EffectiveAddressCalculation::SP);
+        let ss = self.registers.read_segment(&SegmentRegister::SS);
+        self.registers
+            .write(&EffectiveAddressCalculation::SP, sp.wrapping_add(2));
+        self.read_memory(ss, sp, Width::Word)
+    }
+
+    // Reads an indirect call's target, which is always a word whatever the
+    // decoder left in `width`.
+    fn read_word(
+        &mut self,
+        decoded: &DecodedInstruction,
+        operand: &Operand,
+    ) -> Result<u16, SimulateError> {
+        match operand {
+            Operand::Memory(address) => {
+                let (segment, offset) = self.effective_address(address);
+                Ok(self.read_memory(segment, offset, Width::Word))
+            }
+            _ => self.read_operand(decoded, operand),
+        }
+    }
+
+    /// The segment and offset a memory operand refers to. BP-based
+    /// addresses default to SS, all others to DS, and the offset wraps at
+    /// 16 bits.
+    pub fn effective_address(&self, address: &MemoryAddress) -> (u16, u16) {
+        let read = |register| self.registers.read(&register).unwrap_or_default();
+
+        let (base, default_segment) = match address.base {
+            Some(EffectiveAddressCalculation::BxSi) => (
+                read(EffectiveAddressCalculation::BX)
+                    .wrapping_add(read(EffectiveAddressCalculation::SI)),
+                SegmentRegister::DS,
+            ),
+            Some(EffectiveAddressCalculation::BxDi) => (
+                read(EffectiveAddressCalculation::BX)
+                    .wrapping_add(read(EffectiveAddressCalculation::DI)),
+                SegmentRegister::DS,
+            ),
+            Some(EffectiveAddressCalculation::BpSi) => (
+                read(EffectiveAddressCalculation::BP)
+                    .wrapping_add(read(EffectiveAddressCalculation::SI)),
+                SegmentRegister::SS,
+            ),
+            Some(EffectiveAddressCalculation::BpDi) => (
+                read(EffectiveAddressCalculation::BP)
+                    .wrapping_add(read(EffectiveAddressCalculation::DI)),
+                SegmentRegister::SS,
+            ),
+            Some(EffectiveAddressCalculation::Bp) => {
+                (read(EffectiveAddressCalculation::BP), SegmentRegister::SS)
+            }
+            Some(base) => (read(base), SegmentRegister::DS),
+            // Direct addresses are the displacement alone.
+            None => (0, SegmentRegister::DS),
+        };
+
+        let segment_register = address.segment.unwrap_or(default_segment);
+        let offset = base.wrapping_add(address.displacement as u16);
+
+        (self.registers.read_segment(&segment_register), offset)
+    }
+
+    fn read_operand(
+        &mut self,
+        decoded: &DecodedInstruction,
+        operand: &Operand,
+    ) -> Result<u16, SimulateError> {
+        match operand {
+            Operand::Register(register) => self
+                .registers
+                .read(register)
+                .ok_or_else(|| unsupported(decoded)),
+            Operand::SegmentRegister(segment_register) => {
+                Ok(self.registers.read_segment(segment_register))
+            }
+            Operand::Memory(address) => {
+                let width = decoded.width.ok_or_else(|| unsupported(decoded))?;
+                let (segment, offset) = self.effective_address(address);
+                Ok(self.read_memory(segment, offset, width))
+            }
+            // Byte immediates are cut down to their low byte on write.
+            Operand::Immediate(data) => Ok(*data as u16),
+            _ => Err(unsupported(decoded)),
+        }
+    }
+
+    fn write_operand(
+        &mut self,
+        decoded: &DecodedInstruction,
+        operand: &Operand,
+        value: u16,
+    ) -> Result<(), SimulateError> {
+        match operand {
+            Operand::Register(register) => self
+                .registers
+                .write(register, value)
+                .ok_or_else(|| unsupported(decoded)),
+            Operand::SegmentRegister(segment_register) => {
+                self.registers.write_segment(segment_register, value);
+                Ok(())
+            }
+            Operand::Memory(address) => {
+                let width = decoded.width.ok_or_else(|| unsupported(decoded))?;
+                let (segment, offset) = self.effective_address(address);
+                self.write_memory(segment, offset, width, value);
+                Ok(())
+            }
+            _ => Err(unsupported(decoded)),
+        }
+    }
+}
+
+// Every register that differs, as `ax:0x0->0x1 `, then the flags as
+// `flags:->PZ `.
+fn format_changes(before: &Registers, after: &Registers, options: &TraceOptions) -> String {
+    let mut changes: String = before
+        .named()
+        .into_iter()
+        .zip(after.named())
+        .filter(|((name, _), _)| options.ip || *name != "ip")
+        .filter(|((_, old), (_, new))| old != new)
+        .map(|((name, old), (_, new))| format!("{}:{:#x}->{:#x} ", name, old, new))
+        .collect();
+
+    if before.flags != after.flags {
+        changes.push_str(format!("flags:{}->{} ", before.flags, after.flags).as_str());
+    }
+
+    changes
+}
+
+fn format_registers(registers: &Registers, options: &TraceOptions) -> String {
+    let mut dump: String = registers
+        .named()
+        .into_iter()
+        .filter(|(name, _)| options.ip || *name != "ip")
+        .filter(|(_, value)| *value != 0)
+        .map(|(name, value)| format!("      {}: {:#06x} ({})\n", name, value, value))
+        .collect();
+
+    if registers.flags.bits != 0 {
+        dump.push_str(format!("   flags: {}\n", registers.flags).as_str());
+    }
+
+    dump
+}
+
+impl Simulator {
+    /// Copies `contents` in at CS:IP and returns the physical addresses it
+    /// fills.
+    pub fn load(&mut self, contents: &[u8]) -> Range<usize> {
+        let start = self.instruction_address();
+        self.memory.load(start, contents);
+
+        start..start + contents.len()
+    }
+
+    /// Steps and describes what happened as a trace line without the line
+    /// break, `mov ax, 1 ; ax:0x0->0x1 ip:0x0->0x3 `.
+    pub fn trace_step(&mut self, options: &TraceOptions) -> Result<String, SimulateError> {
+        let before = self.registers;
+        let (decoded, execution) = self.step_timed()?;
+
+        let clocks = match options.clocks {
+            Some(cpu) => {
+                let bytes = &self.memory.as_slice()[decoded.offset..][..decoded.length];
+                let clocks = estimate_clocks(&decoded, bytes, &cpu, &execution);
+                self.clocks += clocks.map(|clocks| clocks.total()).unwrap_or_default();
+                format!("{} | ", format_clocks(clocks.as_ref(), self.clocks))
+            }
+            None => String::new(),
+        };
+
+        Ok(format!(
+            "{} ; {}{}",
+            format_trace_instruction(&decoded),
+            clocks,
+            format_changes(&before, &self.registers, options)
+        ))
+    }
+
+    /// Loads `contents` at CS:IP and executes it from there, tracing every
+    /// register and flags change and ending with the registers that aren't
+    /// zero. `name` goes in the heading. Execution stops on `hlt`, when IP
+    /// leaves the loaded image, or at the first instruction it can't run.
+    /// With `options.clocks`, each line's comment starts with the estimated
+    /// clocks and their running total.
+    pub fn run(&mut self, contents: &[u8], name: &str, options: &TraceOptions) -> String {
+        let mut output: String = format!("--- {} execution ---\n", name);
+
+        let image = self.load(contents);
+        let mut steps: usize = 0;
+
+        while image.contains(&self.instruction_address()) && !self.halted {
+            if options
+                .max_steps
+                .is_some_and(|max_steps| steps >= max_steps)
+            {
+                output.push_str(format!("; stopped after {} instructions\n", steps).as_str());
+                break;
+            }
+
+            match self.trace_step(options) {
+                Ok(line) => output.push_str(format!("{}\n", line).as_str()),
+                Err(error) => {
+                    output.push_str(format!("; {}\n", error).as_str());
+                    break;
+                }
+            }
+            steps += 1;
+        }
+
+        output.push_str("\nFinal registers:\n");
+        output.push_str(format_registers(&self.registers, options).as_str());
+        output.push('\n');
+
+        output
+    }
+}
+
+/// Executes `contents` on a fresh simulator, see `Simulator::run`.
+pub fn exec_bin(contents: &[u8], name: &str, options: &TraceOptions) -> String {
+    Simulator::new().run(contents, name, options)
+}
+
+/// Parses a number in decimal or, with `0x`, in hex, the way the command
+/// line, the debugger and its expressions all write them.
+pub fn which_number(text: &str) -> Option<usize> {
+    match text.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+// A number that fits in a word.
+pub(crate) fn which_word(text: &str) -> Option<u16> {
+    which_number(text).and_then(|number| u16::try_from(number).ok())
+}
@@ -0,0 +1,84 @@
+use crate::decoder::constants::Op;
+use crate::decoder::decoded_instruction::Width;
+use crate::simulator::flags::{Flag, Flags};
+
+fn which_mask(width: &Width) -> u32 {
+    match width {
+        Width::Byte => 0x00ff,
+        Width::Word => 0xffff,
+    }
+}
+
+fn which_sign_bit(width: &Width) -> u32 {
+    match width {
+        Width::Byte => 0x0080,
+        Width::Word => 0x8000,
+    }
+}
+
+// `value` read as a signed number with `sign_bit` as its sign.
+fn which_signed(value: u32, sign_bit: u32) -> i32 {
+    if value & sign_bit != 0 {
+        value as i32 - (sign_bit << 1) as i32
+    } else {
+        value as i32
+    }
+}
+
+/// Works out `destination op source` at `width` and the flags it leaves.
+/// Cmp and test produce the result they would write. `None` for any op that
+/// isn't arithmetic or logic.
+pub(crate) fn arithmetic(
+    op: &Op,
+    width: &Width,
+    destination: u16,
+    source: u16,
+    flags: &Flags,
+) -> Option<(u16, Flags)> {
+    let mask = which_mask(width);
+    let sign_bit = which_sign_bit(width);
+    let destination = destination as u32 & mask;
+    let source = source as u32 & mask;
+    let carry_in = flags.get(Flag::Carry) as u32;
+
+    let (result, carry, overflow) = match op {
+        Op::Add | Op::Adc => {
+            let carry_in = if *op == Op::Adc { carry_in } else { 0 };
+            let sum = destination + source + carry_in;
+            let result = sum & mask;
+            let overflow = (destination ^ result) & (source ^ result) & sign_bit != 0;
+
+            (result, sum > mask, overflow)
+        }
+        Op::Sub | Op::Sbb | Op::Cmp => {
+            let borrow_in = if *op == Op::Sbb { carry_in } else { 0 };
+            let result = destination.wrapping_sub(source + borrow_in) & mask;
+            // The signed difference, borrow included, has to fit the width.
+            let difference = which_signed(destination, sign_bit)
+                - which_signed(source, sign_bit)
+                - borrow_in as i32;
+            let overflow = !(-(sign_bit as i32)..sign_bit as i32).contains(&difference);
+
+            (result, destination < source + borrow_in, overflow)
+        }
+        Op::And | Op::Test => (destination & source, false, false),
+        Op::Or => (destination | source, false, false),
+        Op::Xor => (destination ^ source, false, false),
+        _ => return None,
+    };
+
+    let mut flags = *flags;
+    flags.set(Flag::Carry, carry);
+    flags.set(Flag::Parity, (result as u8).count_ones().is_multiple_of(2));
+    // Logic ops leave the auxiliary carry undefined, it is cleared here.
+    flags.set(
+        Flag::Auxiliary,
+        !matches!(op, Op::And | Op::Test | Op::Or | Op::Xor)
+            && (destination ^ source ^ result) & 0x10 != 0,
+    );
+    flags.set(Flag::Zero, result == 0);
+    flags.set(Flag::Sign, result & sign_bit != 0);
+    flags.set(Flag::Overflow, overflow);
+
+    Some((result as u16, flags))
+}
@@ -0,0 +1,654 @@
+use crate::decoder::constants::{EffectiveAddressCalculation, Op, SegmentRegister};
+use crate::decoder::decoded_instruction::Width;
+use crate::formatter::nasm::format_trace_instruction;
+use crate::processor::decode;
+use crate::simulator::expression::Expression;
+use crate::simulator::memory::{Memory, MemoryAccess, MEMORY_SIZE};
+use crate::simulator::registers::{which_register, which_segment_register};
+use crate::simulator::{
+    format_changes, format_registers, which_number, which_word, Simulator, TraceOptions,
+};
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+use std::ops::Range;
+
+const HELP: &str = "\
+s, step [N]             execute N instructions, 1 by default
+n, next                 step, running a call through to its return
+c, continue             run to a breakpoint, hlt or the end of the image
+b, break [IP]           set a breakpoint, or list them
+b, break [IP] if EXPR   stop at IP only when EXPR holds, or without IP
+                        after any instruction that makes it hold
+d, delete IP | if EXPR  remove a breakpoint
+watch ADDRESS [LENGTH]  stop when memory is written, or list watchpoints
+rwatch ADDRESS [LENGTH] stop when memory is read
+awatch ADDRESS [LENGTH] stop when memory is read or written
+unwatch ADDRESS         remove the watchpoints from ADDRESS
+r, registers            show the registers that aren't zero
+x ADDRESS [LENGTH]      show memory, 16 bytes by default
+l, list                 disassemble around IP
+set REGISTER VALUE      change a register, flags included
+w, write ADDRESS BYTE.. change memory
+h, help                 show this
+q, quit                 leave
+ADDRESS is physical, or SEGMENT:OFFSET with a segment register or number.
+Numbers are decimal, or hex with 0x. EXPR compares registers, flags like
+zf, and memory like [ds:si] or word [bx + 2], as in cx == 0 && [si] == 0x41.
+";
+
+// How many instructions `list` shows before and after IP.
+const LIST_CONTEXT: usize = 3;
+
+// How many instructions `continue` and `next` run when the trace options
+// set no limit, so a tight loop still comes back to the prompt.
+const DEFAULT_MAX_STEPS: usize = 100_000;
+
+// Parses the words after `if`, or says what's wrong with them.
+fn which_condition(words: &[&str]) -> Result<Condition, String> {
+    let text = words.join(" ");
+
+    match Expression::parse(&text) {
+        Ok(expression) => Ok(Condition { text, expression }),
+        Err(error) => Err(format!("{} in {}\n", error, text)),
+    }
+}
+
+/// A breakpoint condition with the text it was parsed from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Condition {
+    pub text: String,
+    pub expression: Expression,
+}
+
+/// Stops execution when an instruction reads or writes, as `read` and
+/// `write` say, any byte in `range` of physical addresses.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub range: Range<usize>,
+    pub read: bool,
+    pub write: bool,
+}
+
+impl Watchpoint {
+    fn which_kind(&self) -> &'static str {
+        match (self.read, self.write) {
+            (true, true) => "access",
+            (true, false) => "read",
+            _ => "write",
+        }
+    }
+
+    fn is_triggered_by(&self, access: &MemoryAccess) -> bool {
+        let range = access.range();
+        let overlaps = range.start < self.range.end && self.range.start < range.end;
+
+        overlaps && if access.write { self.write } else { self.read }
+    }
+}
+
+// `write watchpoint on 00100..00110`
+fn format_watchpoint(watchpoint: &Watchpoint) -> String {
+    format!(
+        "{} watchpoint on {:05x}..{:05x}\n",
+        watchpoint.which_kind(),
+        watchpoint.range.start,
+        watchpoint.range.end
+    )
+}
+
+// `breakpoint at 0x000a if cx == 0`
+fn format_breakpoint(ip: &u16, condition: &Option<Condition>) -> String {
+    match condition {
+        Some(condition) => format!("breakpoint at {:#06x} if {}\n", ip, condition.text),
+        None => format!("breakpoint at {:#06x}\n", ip),
+    }
+}
+
+/// A line-oriented debugger over a simulator with a loaded image. It reads
+/// commands from any reader and writes to any writer, so it runs on stdin
+/// and stdout or from a script.
+#[derive(Clone, Debug)]
+pub struct Debugger {
+    pub simulator: Simulator,
+    // IPs to stop at, some only when a condition holds.
+    pub breakpoints: BTreeMap<u16, Option<Condition>>,
+    // Conditions checked after every instruction.
+    pub conditions: Vec<Condition>,
+    pub watchpoints: Vec<Watchpoint>,
+    options: TraceOptions,
+    // The physical addresses the image was loaded at and the IP of its
+    // first byte.
+    image: Range<usize>,
+    start: u16,
+}
+
+impl Debugger {
+    /// Loads `contents` at CS:IP of `simulator`. Steps are traced with
+    /// `options`, and its `max_steps` bounds every `continue`, 100000 when
+    /// it has none.
+    pub fn new(mut simulator: Simulator, contents: &[u8], options: &TraceOptions) -> Self {
+        let start = simulator.registers.ip;
+        let image = simulator.load(contents);
+
+        Debugger {
+            simulator,
+            breakpoints: BTreeMap::new(),
+            conditions: Vec::new(),
+            watchpoints: Vec::new(),
+            options: *options,
+            image,
+            start,
+        }
+    }
+
+    /// Prompts with `> `, reads a command per line and writes what it prints
+    /// until `quit` or the end of `input`.
+    pub fn run(&mut self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        write!(output, "> ")?;
+        output.flush()?;
+
+        for line in input.lines() {
+            match self.command(&line?) {
+                Some(text) => write!(output, "{}> ", text)?,
+                None => break,
+            }
+            output.flush()?;
+        }
+
+        writeln!(output)
+    }
+
+    /// Carries out one command and returns what it prints, `None` to quit.
+    pub fn command(&mut self, line: &str) -> Option<String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        let text = match words.as_slice() {
+            [] => String::new(),
+            ["s" | "step"] => self.step(1),
+            ["s" | "step", count] => match which_number(count) {
+                Some(count) => self.step(count),
+                None => format!("expected a number of steps, found {}\n", count),
+            },
+            ["n" | "next"] => self.next(),
+            ["c" | "continue"] => self.resume(),
+            ["b" | "break"] => self.list_breakpoints(),
+            ["b" | "break", "if", expression @ ..] => self.add_condition(expression),
+            ["b" | "break", ip] => self.add_breakpoint(ip, &[]),
+            ["b" | "break", ip, "if", expression @ ..] => self.add_breakpoint(ip, expression),
+            ["d" | "delete", "if", expression @ ..] => {
+                let text = expression.join(" ");
+                let count = self.conditions.len();
+                self.conditions.retain(|condition| condition.text != text);
+
+                if self.conditions.len() < count {
+                    format!("deleted break if {}\n", text)
+                } else {
+                    format!("no break if {}\n", text)
+                }
+            }
+            ["d" | "delete", ip] => match which_word(ip) {
+                Some(ip) if self.breakpoints.remove(&ip).is_some() => {
+                    format!("deleted breakpoint at {:#06x}\n", ip)
+                }
+                Some(ip) => format!("no breakpoint at {:#06x}\n", ip),
+                None => format!("expected an IP, found {}\n", ip),
+            },
+            ["watch"] => self.list_watchpoints(),
+            [kind @ ("watch" | "rwatch" | "awatch"), address, length @ ..] if length.len() < 2 => {
+                let length = length.first().copied().unwrap_or("1");
+                self.add_watchpoint(address, length, *kind != "watch", *kind != "rwatch")
+            }
+            ["unwatch", address] => match self.which_address(address) {
+                Some(address) => {
+                    let count = self.watchpoints.len();
+                    self.watchpoints
+                        .retain(|watchpoint| watchpoint.range.start != address);
+                    format!(
+                        "deleted {} watchpoints at {:05x}\n",
+                        count - self.watchpoints.len(),
+                        address
+                    )
+                }
+                None => format!("expected an address, found {}\n", address),
+            },
+            ["r" | "registers"] => {
+                format_registers(&self.simulator.registers, &TraceOptions::default())
+            }
+            ["x", address] => self.examine(address, "16"),
+            ["x", address, length] => self.examine(address, length),
+            ["l" | "list"] => self.list(),
+            ["set", register, value] => self.set(register, value),
+            ["w" | "write", address, bytes @ ..] if !bytes.is_empty() => self.write(address, bytes),
+            ["h" | "help"] => String::from(HELP),
+            ["q" | "quit"] => return None,
+            [command, ..] => format!("unknown command {}, try help\n", command),
+        };
+
+        Some(text)
+    }
+
+    fn add_breakpoint(&mut self, ip: &str, expression: &[&str]) -> String {
+        let Some(ip) = which_word(ip) else {
+            return format!("expected an IP, found {}\n", ip);
+        };
+
+        let condition = if expression.is_empty() {
+            None
+        } else {
+            match which_condition(expression) {
+                Ok(condition) => Some(condition),
+                Err(error) => return error,
+            }
+        };
+
+        let text = format_breakpoint(&ip, &condition);
+        self.breakpoints.insert(ip, condition);
+        text
+    }
+
+    fn add_condition(&mut self, expression: &[&str]) -> String {
+        match which_condition(expression) {
+            Ok(condition) => {
+                let text = format!("break if {}\n", condition.text);
+                self.conditions.push(condition);
+                text
+            }
+            Err(error) => error,
+        }
+    }
+
+    fn add_watchpoint(&mut self, address: &str, length: &str, read: bool, write: bool) -> String {
+        // The range has to end inside the megabyte.
+        let range = match (self.which_address(address), which_number(length)) {
+            (Some(start), Some(length)) => start
+                .checked_add(length)
+                .filter(|end| *end <= MEMORY_SIZE)
+                .map(|end| start..end),
+            _ => None,
+        };
+        let Some(range) = range else {
+            return format!(
+                "expected an address and a length, found {} {}\n",
+                address, length
+            );
+        };
+
+        let watchpoint = Watchpoint { range, read, write };
+        let text = format_watchpoint(&watchpoint);
+        self.watchpoints.push(watchpoint);
+        text
+    }
+
+    fn list_watchpoints(&self) -> String {
+        if self.watchpoints.is_empty() {
+            return String::from("no watchpoints\n");
+        }
+
+        self.watchpoints.iter().map(format_watchpoint).collect()
+    }
+
+    // Which conditions hold, to tell afterwards which ones an instruction
+    // made hold.
+    fn which_held(&self) -> Vec<bool> {
+        self.conditions
+            .iter()
+            .map(|condition| condition.expression.holds(&self.simulator))
+            .collect()
+    }
+
+    // What the instruction just executed set off: the first watchpoint one
+    // of its accesses falls in, then the first condition that didn't hold
+    // before it, by `held`, and does now.
+    fn which_trigger(&self, held: &[bool]) -> Option<String> {
+        for access in &self.simulator.accesses {
+            if let Some(watchpoint) = self
+                .watchpoints
+                .iter()
+                .find(|watchpoint| watchpoint.is_triggered_by(access))
+            {
+                let width = match access.width {
+                    Width::Byte => "byte",
+                    Width::Word => "word",
+                };
+                let value = if access.write {
+                    format!("{:#x}->{:#x}", access.old, access.new)
+                } else {
+                    format!("{:#x}", access.new)
+                };
+
+                return Some(format!(
+                    "; {} watchpoint on {:05x}..{:05x}, {} at {:05x}: {}\n",
+                    watchpoint.which_kind(),
+                    watchpoint.range.start,
+                    watchpoint.range.end,
+                    width,
+                    access.address,
+                    value
+                ));
+            }
+        }
+
+        self.conditions
+            .iter()
+            .zip(held)
+            .find(|(condition, held)| !**held && condition.expression.holds(&self.simulator))
+            .map(|(condition, _)| format!("; {} holds\n", condition.text))
+    }
+
+    fn ip(&self) -> u16 {
+        self.simulator.registers.ip
+    }
+
+    fn sp(&self) -> u16 {
+        self.simulator
+            .registers
+            .read(&EffectiveAddressCalculation::SP)
+            .unwrap_or_default()
+    }
+
+    // Why execution can't go on by itself, if it can't.
+    fn which_stop(&self) -> Option<&'static str> {
+        if self.simulator.halted {
+            Some("halted")
+        } else if !self.image.contains(&self.simulator.instruction_address()) {
+            Some("IP is outside the image")
+        } else {
+            None
+        }
+    }
+
+    fn step(&mut self, count: usize) -> String {
+        let mut output = String::new();
+
+        for _ in 0..count {
+            if let Some(stop) = self.which_stop() {
+                output.push_str(format!("; {}\n", stop).as_str());
+                break;
+            }
+
+            let held = self.which_held();
+            match self.simulator.trace_step(&self.options) {
+                Ok(line) => output.push_str(format!("{}\n", line).as_str()),
+                Err(error) => {
+                    output.push_str(format!("; {}\n", error).as_str());
+                    break;
+                }
+            }
+
+            if let Some(trigger) = self.which_trigger(&held) {
+                output.push_str(trigger.as_str());
+                break;
+            }
+        }
+
+        output
+    }
+
+    // Steps, unless the instruction at IP is a call: then it runs until the
+    // call returns and traces the whole of it as one line.
+    fn next(&mut self) -> String {
+        let decoded = match self.simulator.fetch() {
+            Ok(decoded) if decoded.op == Op::Call && self.which_stop().is_none() => decoded,
+            _ => return self.step(1),
+        };
+
+        let before = self.simulator.registers;
+        let returned = self.ip().wrapping_add(decoded.length as u16);
+
+        // A call that doesn't come back stops like `continue` would.
+        let stop = self.run_until(Some((returned, self.sp())));
+
+        match stop {
+            Some(stop) => stop,
+            None => format!(
+                "{} ; {}\n",
+                format_trace_instruction(&decoded),
+                format_changes(&before, &self.simulator.registers, &self.options)
+            ),
+        }
+    }
+
+    fn resume(&mut self) -> String {
+        let stop = self.run_until(None);
+
+        let mut output = stop.unwrap_or_default();
+        if self.which_stop().is_none() {
+            output.push_str(self.format_line(self.ip()).as_str());
+        }
+
+        output
+    }
+
+    // Executes without printing until IP and SP come back to `until`, gets
+    // to a breakpoint whose condition holds, or execution stops. SP keeps a
+    // recursive call returning to the same IP deeper in the stack from
+    // counting, and may end up above `until` after a `ret` that pops
+    // arguments. A watchpoint or condition stops it after the instruction
+    // that set it off, which is traced. Returns why it stopped, `None` when
+    // it reached `until`.
+    fn run_until(&mut self, until: Option<(u16, u16)>) -> Option<String> {
+        let max_steps = self.options.max_steps.unwrap_or(DEFAULT_MAX_STEPS);
+        let mut steps: usize = 0;
+
+        loop {
+            let returned = until.is_some_and(|(ip, sp)| ip == self.ip() && self.sp() >= sp);
+            if steps > 0 && returned {
+                return None;
+            }
+            if let Some(stop) = self.which_stop() {
+                return Some(format!("; {}, after {} instructions\n", stop, steps));
+            }
+            if steps > 0 {
+                match self.breakpoints.get(&self.ip()) {
+                    Some(Some(condition)) if condition.expression.holds(&self.simulator) => {
+                        return Some(format!(
+                            "; breakpoint at {:#06x} if {}, after {} instructions\n",
+                            self.ip(),
+                            condition.text,
+                            steps
+                        ));
+                    }
+                    Some(None) => {
+                        return Some(format!(
+                            "; breakpoint at {:#06x}, after {} instructions\n",
+                            self.ip(),
+                            steps
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+            if steps >= max_steps {
+                return Some(format!("; stopped after {} instructions\n", steps));
+            }
+
+            let held = self.which_held();
+            let line = match self.simulator.trace_step(&self.options) {
+                Ok(line) => line,
+                Err(error) => return Some(format!("; {}\n", error)),
+            };
+            steps += 1;
+
+            if let Some(trigger) = self.which_trigger(&held) {
+                return Some(format!("{}\n{}", line, trigger));
+            }
+        }
+    }
+
+    fn list_breakpoints(&self) -> String {
+        if self.breakpoints.is_empty() && self.conditions.is_empty() {
+            return String::from("no breakpoints\n");
+        }
+
+        self.breakpoints
+            .iter()
+            .map(|(ip, condition)| format_breakpoint(ip, condition))
+            .chain(
+                self.conditions
+                    .iter()
+                    .map(|condition| format!("break if {}\n", condition.text)),
+            )
+            .collect()
+    }
+
+    // The instructions around IP, decoded from the start of the image so
+    // the ones before it line up. An IP the walk doesn't land on is listed
+    // alone.
+    fn list(&self) -> String {
+        let mut ips: Vec<u16> = Vec::new();
+        let mut ip = self.start;
+
+        // An image of a whole segment would have IP wrap round forever.
+        while self.image.contains(&self.physical(ip)) && ips.len() < self.image.len() {
+            ips.push(ip);
+            let length = match decode(self.simulator.memory.as_slice(), self.physical(ip)) {
+                Ok(decoded) => decoded.length,
+                Err(_) => 1,
+            };
+            ip = ip.wrapping_add(length as u16);
+        }
+
+        let lines: Vec<u16> = match ips.iter().position(|ip| *ip == self.ip()) {
+            Some(index) => {
+                let first = index.saturating_sub(LIST_CONTEXT);
+                let last = (index + LIST_CONTEXT + 1).min(ips.len());
+                ips[first..last].to_vec()
+            }
+            None => vec![self.ip()],
+        };
+
+        lines.into_iter().map(|ip| self.format_line(ip)).collect()
+    }
+
+    fn physical(&self, ip: u16) -> usize {
+        Memory::physical(
+            self.simulator.registers.read_segment(&SegmentRegister::CS),
+            ip,
+        )
+    }
+
+    // `=> 0003  b5 f4             mov ch, 244`: IP is marked with `=>` and
+    // breakpoints with `*`.
+    fn format_line(&self, ip: u16) -> String {
+        let marker = if ip == self.ip() {
+            "=>"
+        } else if self.breakpoints.contains_key(&ip) {
+            " *"
+        } else {
+            "  "
+        };
+
+        let address = self.physical(ip);
+        let (length, text) = match decode(self.simulator.memory.as_slice(), address) {
+            Ok(decoded) => (decoded.length, format_trace_instruction(&decoded)),
+            Err(error) => (1, format!("; {}", error)),
+        };
+
+        let bytes: Vec<String> = self
+            .simulator
+            .memory
+            .slice(address, length)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        format!("{} {:04x}  {:<18}{}\n", marker, ip, bytes.join(" "), text)
+    }
+
+    // A physical address, or `SEGMENT:OFFSET` with a segment register or a
+    // number for the segment.
+    fn which_address(&self, text: &str) -> Option<usize> {
+        match text.split_once(':') {
+            Some((segment, offset)) => {
+                let segment = match which_segment_register(segment) {
+                    Some(segment_register) => {
+                        self.simulator.registers.read_segment(&segment_register)
+                    }
+                    None => which_word(segment)?,
+                };
+                Some(Memory::physical(segment, which_word(offset)?))
+            }
+            None => which_number(text).filter(|address| *address < MEMORY_SIZE),
+        }
+    }
+
+    // Sixteen bytes a line, each line led by its physical address. It wraps
+    // at 1 MiB, so more than that would only repeat.
+    fn examine(&self, address: &str, length: &str) -> String {
+        let length_in_memory = which_number(length).filter(|length| *length <= MEMORY_SIZE);
+        let (Some(address), Some(length)) = (self.which_address(address), length_in_memory) else {
+            return format!(
+                "expected an address and a length, found {} {}\n",
+                address, length
+            );
+        };
+
+        self.simulator
+            .memory
+            .slice(address, length)
+            .chunks(16)
+            .enumerate()
+            .map(|(line, bytes)| {
+                let bytes: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+                let address = (address + line * 16) % MEMORY_SIZE;
+                format!("{:05x}: {}\n", address, bytes.join(" "))
+            })
+            .collect()
+    }
+
+    fn set(&mut self, register: &str, value: &str) -> String {
+        let Some(value) = which_word(value) else {
+            return format!("expected a 16-bit value, found {}\n", value);
+        };
+        let registers = &mut self.simulator.registers;
+
+        match register {
+            "ip" => {
+                registers.ip = value;
+                // Moving IP is the way on after `hlt`.
+                self.simulator.halted = false;
+            }
+            "flags" => registers.flags.bits = value,
+            _ => {
+                if let Some(segment_register) = which_segment_register(register) {
+                    registers.write_segment(&segment_register, value);
+                } else if let Some(general) = which_register(register) {
+                    if register.ends_with(['l', 'h']) && value > 0xff {
+                        return format!("{} only holds a byte, found {:#x}\n", register, value);
+                    }
+                    registers.write(&general, value);
+                } else {
+                    return format!("unknown register {}\n", register);
+                }
+            }
+        }
+
+        format!("{}: {:#06x} ({})\n", register, value, value)
+    }
+
+    fn write(&mut self, address: &str, bytes: &[&str]) -> String {
+        let Some(address) = self.which_address(address) else {
+            return format!("expected an address, found {}\n", address);
+        };
+
+        let mut values: Vec<u8> = Vec::new();
+        for byte in bytes {
+            match which_number(byte).and_then(|byte| u8::try_from(byte).ok()) {
+                Some(byte) => values.push(byte),
+                None => return format!("expected a byte, found {}\n", byte),
+            }
+        }
+
+        self.simulator.memory.load(address, &values);
+
+        format!(
+            "{:05x}: {}\n",
+            address,
+            values
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<Vec<String>>()
+                .join(" ")
+        )
+    }
+}
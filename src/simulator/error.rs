@@ -0,0 +1,74 @@
+use crate::decoder::error::DecodeError;
+use std::{error::Error, fmt};
+
+// Simulate Errors
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SimulateError {
+    // The bytes at CS:IP don't decode.
+    Decode(DecodeError),
+    // The instruction at physical address `offset`, formatted as `line`,
+    // decoded but isn't one the simulator executes.
+    Unsupported { offset: usize, line: String },
+}
+
+impl SimulateError {
+    pub fn offset(&self) -> usize {
+        match self {
+            SimulateError::Decode(error) => error.offset(),
+            SimulateError::Unsupported { offset, .. } => *offset,
+        }
+    }
+}
+
+impl fmt::Display for SimulateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SimulateError::Decode(error) => write!(f, "{}", error),
+            SimulateError::Unsupported { offset, line } => {
+                write!(f, "cannot execute `{}` at offset {}", line, offset)
+            }
+        }
+    }
+}
+
+impl Error for SimulateError {}
+
+impl From<DecodeError> for SimulateError {
+    fn from(error: DecodeError) -> Self {
+        SimulateError::Decode(error)
+    }
+}
+
+// Expression Errors, `offset` counts characters from the start
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExpressionError {
+    // `token` is no register, flag, number or operator, or can't go where
+    // it is.
+    UnexpectedToken { offset: usize, token: String },
+    // The expression ends where it needs more.
+    UnexpectedEnd { offset: usize },
+}
+
+impl ExpressionError {
+    pub fn offset(&self) -> usize {
+        match self {
+            ExpressionError::UnexpectedToken { offset, .. }
+            | ExpressionError::UnexpectedEnd { offset } => *offset,
+        }
+    }
+}
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExpressionError::UnexpectedToken { offset, token } => {
+                write!(f, "unexpected `{}` at offset {}", token, offset)
+            }
+            ExpressionError::UnexpectedEnd { offset } => {
+                write!(f, "expression ends early at offset {}", offset)
+            }
+        }
+    }
+}
+
+impl Error for ExpressionError {}
@@ -0,0 +1,337 @@
+use crate::decoder::constants::{EffectiveAddressCalculation, SegmentRegister};
+use crate::decoder::decoded_instruction::Width;
+use crate::simulator::error::ExpressionError;
+use crate::simulator::flags::Flag;
+use crate::simulator::registers::{which_register, which_segment_register};
+use crate::simulator::{which_word, Simulator};
+
+// Two-character operators come first so `<=` isn't read as `<`.
+const OPERATORS: [&str; 15] = [
+    "==", "!=", "<=", ">=", "&&", "||", "<", ">", "+", "-", "!", "(", ")", "[", "]",
+];
+
+// Binary Operators
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Add,
+    Sub,
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    And,
+    Or,
+}
+
+/// A condition over the registers, flags and memory of a simulator, like
+/// `cx == 0 && [ds:si] == 0x41`. Values are 16-bit and unsigned, and a
+/// comparison is 1 when it holds and 0 when it doesn't.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expression {
+    Number(u16),
+    Register(EffectiveAddressCalculation),
+    SegmentRegister(SegmentRegister),
+    Ip,
+    Flag(Flag),
+    // `[ds:si + 2]` is a byte, `word [si]` a word. The segment defaults to
+    // DS.
+    Memory {
+        segment: Box<Expression>,
+        offset: Box<Expression>,
+        width: Width,
+    },
+    Not(Box<Expression>),
+    Binary(Box<Expression>, Operator, Box<Expression>),
+}
+
+fn which_flag(name: &str) -> Option<Flag> {
+    let flag = match name {
+        "cf" => Flag::Carry,
+        "pf" => Flag::Parity,
+        "af" => Flag::Auxiliary,
+        "zf" => Flag::Zero,
+        "sf" => Flag::Sign,
+        "tf" => Flag::Trap,
+        "if" => Flag::Interrupt,
+        "df" => Flag::Direction,
+        "of" => Flag::Overflow,
+        _ => return None,
+    };
+
+    Some(flag)
+}
+
+fn which_operator(token: &str) -> Option<Operator> {
+    let operator = match token {
+        "+" => Operator::Add,
+        "-" => Operator::Sub,
+        "==" => Operator::Equal,
+        "!=" => Operator::NotEqual,
+        "<" => Operator::Less,
+        "<=" => Operator::LessOrEqual,
+        ">" => Operator::Greater,
+        ">=" => Operator::GreaterOrEqual,
+        "&&" => Operator::And,
+        "||" => Operator::Or,
+        _ => return None,
+    };
+
+    Some(operator)
+}
+
+// Splits `text` into names, numbers, operators and `:`, each with the
+// offset it starts at. Anything else is a token of its own for the parser
+// to reject.
+fn tokenize(text: &str) -> Vec<(usize, &str)> {
+    let mut tokens = Vec::new();
+    let mut offset = 0;
+
+    while offset < text.len() {
+        let rest = &text[offset..];
+        let character = rest.chars().next().unwrap_or_default();
+
+        let length = if character.is_whitespace() {
+            offset += character.len_utf8();
+            continue;
+        } else if character.is_ascii_alphanumeric() || character == '_' {
+            rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len())
+        } else {
+            OPERATORS
+                .iter()
+                .find(|operator| rest.starts_with(**operator))
+                .map(|operator| operator.len())
+                .unwrap_or(character.len_utf8())
+        };
+
+        tokens.push((offset, &rest[..length]));
+        offset += length;
+    }
+
+    tokens
+}
+
+struct Parser<'a> {
+    tokens: Vec<(usize, &'a str)>,
+    index: usize,
+    end: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.index).map(|(_, token)| *token)
+    }
+
+    fn next(&mut self) -> Result<(usize, &'a str), ExpressionError> {
+        let token = self
+            .tokens
+            .get(self.index)
+            .copied()
+            .ok_or(ExpressionError::UnexpectedEnd { offset: self.end })?;
+        self.index += 1;
+
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), ExpressionError> {
+        match self.next()? {
+            (_, token) if token == expected => Ok(()),
+            (offset, token) => Err(ExpressionError::UnexpectedToken {
+                offset,
+                token: token.to_string(),
+            }),
+        }
+    }
+
+    // Operators bind loosest first: `||`, `&&`, comparisons, then `+` and
+    // `-`. A level takes the operators in `operators`.
+    fn binary(
+        &mut self,
+        operators: &[Operator],
+        operand: fn(&mut Self) -> Result<Expression, ExpressionError>,
+    ) -> Result<Expression, ExpressionError> {
+        let mut expression = operand(self)?;
+
+        while let Some(operator) = self
+            .peek()
+            .and_then(which_operator)
+            .filter(|operator| operators.contains(operator))
+        {
+            self.index += 1;
+            expression =
+                Expression::Binary(Box::new(expression), operator, Box::new(operand(self)?));
+        }
+
+        Ok(expression)
+    }
+
+    fn or(&mut self) -> Result<Expression, ExpressionError> {
+        self.binary(&[Operator::Or], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Expression, ExpressionError> {
+        self.binary(&[Operator::And], Self::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Expression, ExpressionError> {
+        self.binary(
+            &[
+                Operator::Equal,
+                Operator::NotEqual,
+                Operator::Less,
+                Operator::LessOrEqual,
+                Operator::Greater,
+                Operator::GreaterOrEqual,
+            ],
+            Self::sum,
+        )
+    }
+
+    fn sum(&mut self) -> Result<Expression, ExpressionError> {
+        self.binary(&[Operator::Add, Operator::Sub], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expression, ExpressionError> {
+        if self.peek() == Some("!") {
+            self.index += 1;
+            return Ok(Expression::Not(Box::new(self.unary()?)));
+        }
+
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expression, ExpressionError> {
+        let (offset, token) = self.next()?;
+
+        let expression = match token {
+            "(" => {
+                let expression = self.or()?;
+                self.expect(")")?;
+                expression
+            }
+            "[" => self.memory(Width::Byte)?,
+            "byte" | "word" => {
+                self.expect("[")?;
+                let width = if token == "word" {
+                    Width::Word
+                } else {
+                    Width::Byte
+                };
+                self.memory(width)?
+            }
+            "ip" => Expression::Ip,
+            _ => {
+                if let Some(number) = which_word(token) {
+                    Expression::Number(number)
+                } else if let Some(register) = which_register(token) {
+                    Expression::Register(register)
+                } else if let Some(segment_register) = which_segment_register(token) {
+                    Expression::SegmentRegister(segment_register)
+                } else if let Some(flag) = which_flag(token) {
+                    Expression::Flag(flag)
+                } else {
+                    return Err(ExpressionError::UnexpectedToken {
+                        offset,
+                        token: token.to_string(),
+                    });
+                }
+            }
+        };
+
+        Ok(expression)
+    }
+
+    // The inside of `[...]` after the `[`, `segment:offset` or an offset
+    // in DS.
+    fn memory(&mut self, width: Width) -> Result<Expression, ExpressionError> {
+        let segmented = self.tokens.get(self.index + 1).map(|(_, token)| *token) == Some(":");
+
+        let segment = if segmented {
+            let segment = self.primary()?;
+            self.expect(":")?;
+            segment
+        } else {
+            Expression::SegmentRegister(SegmentRegister::DS)
+        };
+
+        let offset = self.sum()?;
+        self.expect("]")?;
+
+        Ok(Expression::Memory {
+            segment: Box::new(segment),
+            offset: Box::new(offset),
+            width,
+        })
+    }
+}
+
+impl Expression {
+    /// Parses `text`, which has to be one whole expression.
+    pub fn parse(text: &str) -> Result<Expression, ExpressionError> {
+        let mut parser = Parser {
+            tokens: tokenize(text),
+            index: 0,
+            end: text.len(),
+        };
+
+        let expression = parser.or()?;
+
+        match parser.tokens.get(parser.index) {
+            Some((offset, token)) => Err(ExpressionError::UnexpectedToken {
+                offset: *offset,
+                token: token.to_string(),
+            }),
+            None => Ok(expression),
+        }
+    }
+
+    /// The value of the expression against the state of `simulator`.
+    /// Reading memory here doesn't count as an access.
+    pub fn evaluate(&self, simulator: &Simulator) -> u16 {
+        let registers = &simulator.registers;
+
+        match self {
+            Expression::Number(number) => *number,
+            Expression::Register(register) => registers.read(register).unwrap_or_default(),
+            Expression::SegmentRegister(segment_register) => {
+                registers.read_segment(segment_register)
+            }
+            Expression::Ip => registers.ip,
+            Expression::Flag(flag) => registers.flags.get(*flag) as u16,
+            Expression::Memory {
+                segment,
+                offset,
+                width,
+            } => simulator.memory.read(
+                segment.evaluate(simulator),
+                offset.evaluate(simulator),
+                width,
+            ),
+            Expression::Not(expression) => (expression.evaluate(simulator) == 0) as u16,
+            Expression::Binary(left, operator, right) => {
+                let left = left.evaluate(simulator);
+                let right = right.evaluate(simulator);
+
+                match operator {
+                    Operator::Add => left.wrapping_add(right),
+                    Operator::Sub => left.wrapping_sub(right),
+                    Operator::Equal => (left == right) as u16,
+                    Operator::NotEqual => (left != right) as u16,
+                    Operator::Less => (left < right) as u16,
+                    Operator::LessOrEqual => (left <= right) as u16,
+                    Operator::Greater => (left > right) as u16,
+                    Operator::GreaterOrEqual => (left >= right) as u16,
+                    Operator::And => (left != 0 && right != 0) as u16,
+                    Operator::Or => (left != 0 || right != 0) as u16,
+                }
+            }
+        }
+    }
+
+    /// Whether the expression isn't 0.
+    pub fn holds(&self, simulator: &Simulator) -> bool {
+        self.evaluate(simulator) != 0
+    }
+}
@@ -0,0 +1,91 @@
+use std::fmt;
+
+// Flags
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flag {
+    Carry,
+    Parity,
+    Auxiliary,
+    Zero,
+    Sign,
+    Trap,
+    Interrupt,
+    Direction,
+    Overflow,
+}
+
+// In bit order, which is also the order the trace writes them in.
+const FLAGS: [Flag; 9] = [
+    Flag::Carry,
+    Flag::Parity,
+    Flag::Auxiliary,
+    Flag::Zero,
+    Flag::Sign,
+    Flag::Trap,
+    Flag::Interrupt,
+    Flag::Direction,
+    Flag::Overflow,
+];
+
+impl Flag {
+    pub fn bit(&self) -> u16 {
+        match self {
+            Flag::Carry => 1 << 0,
+            Flag::Parity => 1 << 2,
+            Flag::Auxiliary => 1 << 4,
+            Flag::Zero => 1 << 6,
+            Flag::Sign => 1 << 7,
+            Flag::Trap => 1 << 8,
+            Flag::Interrupt => 1 << 9,
+            Flag::Direction => 1 << 10,
+            Flag::Overflow => 1 << 11,
+        }
+    }
+
+    pub fn letter(&self) -> char {
+        match self {
+            Flag::Carry => 'C',
+            Flag::Parity => 'P',
+            Flag::Auxiliary => 'A',
+            Flag::Zero => 'Z',
+            Flag::Sign => 'S',
+            Flag::Trap => 'T',
+            Flag::Interrupt => 'I',
+            Flag::Direction => 'D',
+            Flag::Overflow => 'O',
+        }
+    }
+}
+
+/// The 8086 FLAGS word. It displays as the letters of the flags that are
+/// set, `PZ` for parity and zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Flags {
+    pub bits: u16,
+}
+
+impl Flags {
+    pub fn get(&self, flag: Flag) -> bool {
+        self.bits & flag.bit() != 0
+    }
+
+    pub fn set(&mut self, flag: Flag, value: bool) {
+        if value {
+            self.bits |= flag.bit();
+        } else {
+            self.bits &= !flag.bit();
+        }
+    }
+}
+
+impl fmt::Display for Flags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let letters: String = FLAGS
+            .iter()
+            .filter(|flag| self.get(**flag))
+            .map(Flag::letter)
+            .collect();
+
+        write!(f, "{}", letters)
+    }
+}
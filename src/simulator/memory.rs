@@ -0,0 +1,125 @@
+use crate::decoder::decoded_instruction::Width;
+use std::fmt;
+use std::ops::Range;
+
+/// The 8086 addresses 1 MiB, 20 bits.
+pub const MEMORY_SIZE: usize = 1 << 20;
+
+/// One read or write an instruction made, at a physical address. A read
+/// has the same `old` and `new` value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryAccess {
+    pub write: bool,
+    pub address: usize,
+    pub width: Width,
+    pub old: u16,
+    pub new: u16,
+}
+
+impl MemoryAccess {
+    /// The physical addresses the access touches.
+    pub fn range(&self) -> Range<usize> {
+        match self.width {
+            Width::Byte => self.address..self.address + 1,
+            Width::Word => self.address..self.address + 2,
+        }
+    }
+}
+
+/// The simulated address space, read and written through segment:offset.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Memory {
+    bytes: Vec<u8>,
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Memory {
+            bytes: vec![0; MEMORY_SIZE],
+        }
+    }
+}
+
+// A megabyte of bytes is no use in a debug print.
+impl fmt::Debug for Memory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Memory {{ {} bytes }}", self.bytes.len())
+    }
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Memory::default()
+    }
+
+    /// The physical address of `segment:offset`, wrapping at 1 MiB as the
+    /// 8086 does.
+    pub fn physical(segment: u16, offset: u16) -> usize {
+        (((segment as usize) << 4) + offset as usize) % MEMORY_SIZE
+    }
+
+    /// The whole address space, physical address 0 first.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn read_byte(&self, address: usize) -> u8 {
+        self.bytes[address % MEMORY_SIZE]
+    }
+
+    pub fn write_byte(&mut self, address: usize, value: u8) {
+        self.bytes[address % MEMORY_SIZE] = value;
+    }
+
+    /// Reads a byte or a little-endian word at `segment:offset`. The high
+    /// byte of a word at offset 0xffff comes from offset 0 of the segment.
+    pub fn read(&self, segment: u16, offset: u16, width: &Width) -> u16 {
+        let low = self.read_byte(Memory::physical(segment, offset)) as u16;
+
+        match width {
+            Width::Byte => low,
+            Width::Word => {
+                let high = self.read_byte(Memory::physical(segment, offset.wrapping_add(1)));
+                low | (high as u16) << 8
+            }
+        }
+    }
+
+    /// Writes a byte or a little-endian word at `segment:offset`, wrapping
+    /// within the segment like `read`.
+    pub fn write(&mut self, segment: u16, offset: u16, width: &Width, value: u16) {
+        self.write_byte(Memory::physical(segment, offset), value as u8);
+
+        if *width == Width::Word {
+            let address = Memory::physical(segment, offset.wrapping_add(1));
+            self.write_byte(address, (value >> 8) as u8);
+        }
+    }
+
+    /// Copies `bytes` in from physical `address` on, wrapping at 1 MiB.
+    pub fn load(&mut self, address: usize, bytes: &[u8]) {
+        for (index, byte) in bytes.iter().enumerate() {
+            self.write_byte(address + index, *byte);
+        }
+    }
+
+    /// The `length` bytes from physical `address` on, wrapping at 1 MiB.
+    pub fn slice(&self, address: usize, length: usize) -> Vec<u8> {
+        (0..length)
+            .map(|index| self.read_byte(address + index))
+            .collect()
+    }
+
+    /// Renders `height` rows of `width` pixels from physical `address` on as
+    /// a binary PPM image. Every four bytes are one pixel, red, green, blue
+    /// and alpha. PPM has no alpha, so it is dropped.
+    pub fn render_ppm(&self, address: usize, width: usize, height: usize) -> Vec<u8> {
+        let mut image = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+
+        for pixel in self.slice(address, width * height * 4).chunks(4) {
+            image.extend_from_slice(&pixel[..3]);
+        }
+
+        image
+    }
+}
@@ -0,0 +1,149 @@
+use crate::decoder::constants::{EffectiveAddressCalculation, SegmentRegister};
+use crate::simulator::flags::Flags;
+
+// The byte of a general register an operand names.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Part {
+    Low,
+    High,
+    Whole,
+}
+
+// General registers are kept in the order the final dump lists them.
+const GENERAL_NAMES: [&str; 8] = ["ax", "bx", "cx", "dx", "sp", "bp", "si", "di"];
+const SEGMENT_NAMES: [&str; 4] = ["es", "cs", "ss", "ds"];
+
+fn which_slot(register: &EffectiveAddressCalculation) -> Option<(usize, Part)> {
+    let slot = match register {
+        EffectiveAddressCalculation::AL => (0, Part::Low),
+        EffectiveAddressCalculation::AH => (0, Part::High),
+        EffectiveAddressCalculation::AX => (0, Part::Whole),
+        EffectiveAddressCalculation::BL => (1, Part::Low),
+        EffectiveAddressCalculation::BH => (1, Part::High),
+        EffectiveAddressCalculation::BX | EffectiveAddressCalculation::Bx => (1, Part::Whole),
+        EffectiveAddressCalculation::CL => (2, Part::Low),
+        EffectiveAddressCalculation::CH => (2, Part::High),
+        EffectiveAddressCalculation::CX => (2, Part::Whole),
+        EffectiveAddressCalculation::DL => (3, Part::Low),
+        EffectiveAddressCalculation::DH => (3, Part::High),
+        EffectiveAddressCalculation::DX => (3, Part::Whole),
+        EffectiveAddressCalculation::SP => (4, Part::Whole),
+        EffectiveAddressCalculation::BP | EffectiveAddressCalculation::Bp => (5, Part::Whole),
+        EffectiveAddressCalculation::SI | EffectiveAddressCalculation::Si => (6, Part::Whole),
+        EffectiveAddressCalculation::DI | EffectiveAddressCalculation::Di => (7, Part::Whole),
+        // Two-register address calculations are not one register.
+        EffectiveAddressCalculation::BxSi
+        | EffectiveAddressCalculation::BxDi
+        | EffectiveAddressCalculation::BpSi
+        | EffectiveAddressCalculation::BpDi => return None,
+    };
+
+    Some(slot)
+}
+
+fn which_segment_slot(segment_register: &SegmentRegister) -> usize {
+    match segment_register {
+        SegmentRegister::ES => 0,
+        SegmentRegister::CS => 1,
+        SegmentRegister::SS => 2,
+        SegmentRegister::DS => 3,
+    }
+}
+
+/// Looks a general register up by name, `ax` or `al`.
+pub fn which_register(name: &str) -> Option<EffectiveAddressCalculation> {
+    let register = match name {
+        "al" => EffectiveAddressCalculation::AL,
+        "cl" => EffectiveAddressCalculation::CL,
+        "dl" => EffectiveAddressCalculation::DL,
+        "bl" => EffectiveAddressCalculation::BL,
+        "ah" => EffectiveAddressCalculation::AH,
+        "ch" => EffectiveAddressCalculation::CH,
+        "dh" => EffectiveAddressCalculation::DH,
+        "bh" => EffectiveAddressCalculation::BH,
+        "ax" => EffectiveAddressCalculation::AX,
+        "cx" => EffectiveAddressCalculation::CX,
+        "dx" => EffectiveAddressCalculation::DX,
+        "bx" => EffectiveAddressCalculation::BX,
+        "sp" => EffectiveAddressCalculation::SP,
+        "bp" => EffectiveAddressCalculation::BP,
+        "si" => EffectiveAddressCalculation::SI,
+        "di" => EffectiveAddressCalculation::DI,
+        _ => return None,
+    };
+
+    Some(register)
+}
+
+/// Looks a segment register up by name, `ds`.
+pub fn which_segment_register(name: &str) -> Option<SegmentRegister> {
+    let segment_register = match name {
+        "es" => SegmentRegister::ES,
+        "cs" => SegmentRegister::CS,
+        "ss" => SegmentRegister::SS,
+        "ds" => SegmentRegister::DS,
+        _ => return None,
+    };
+
+    Some(segment_register)
+}
+
+/// The 8086 register file. The byte registers are the halves of AX, BX, CX
+/// and DX: writing AL leaves AH alone and shows up in AX.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Registers {
+    general: [u16; 8],
+    segment: [u16; 4],
+    pub ip: u16,
+    pub flags: Flags,
+}
+
+impl Registers {
+    /// Reads a general register, `None` for the two-register address
+    /// calculations.
+    pub fn read(&self, register: &EffectiveAddressCalculation) -> Option<u16> {
+        let (slot, part) = which_slot(register)?;
+        let value = self.general[slot];
+
+        Some(match part {
+            Part::Low => value & 0x00ff,
+            Part::High => value >> 8,
+            Part::Whole => value,
+        })
+    }
+
+    /// Writes a general register. A byte register only takes the low byte
+    /// of `value`.
+    pub fn write(&mut self, register: &EffectiveAddressCalculation, value: u16) -> Option<()> {
+        let (slot, part) = which_slot(register)?;
+        let old = self.general[slot];
+
+        self.general[slot] = match part {
+            Part::Low => (old & 0xff00) | (value & 0x00ff),
+            Part::High => (old & 0x00ff) | (value << 8),
+            Part::Whole => value,
+        };
+
+        Some(())
+    }
+
+    pub fn read_segment(&self, segment_register: &SegmentRegister) -> u16 {
+        self.segment[which_segment_slot(segment_register)]
+    }
+
+    pub fn write_segment(&mut self, segment_register: &SegmentRegister, value: u16) {
+        self.segment[which_segment_slot(segment_register)] = value;
+    }
+
+    /// Every general and segment register and IP by name, in the order the
+    /// final dump lists them.
+    pub fn named(&self) -> Vec<(&'static str, u16)> {
+        GENERAL_NAMES
+            .iter()
+            .zip(self.general)
+            .chain(SEGMENT_NAMES.iter().zip(self.segment))
+            .map(|(name, value)| (*name, value))
+            .chain([("ip", self.ip)])
+            .collect()
+    }
+}
@@ -0,0 +1,122 @@
+use crate::assembler::assemble;
+use crate::assembler::error::AssembleError;
+use crate::decoder::decoded_instruction::DecodedInstruction;
+use crate::formatter::format_instruction;
+use crate::processor::Decoder;
+use std::{error::Error, fmt};
+
+// Verify Errors
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VerifyError {
+    // The rebuilt bytes first differ from the input at `offset`, inside the
+    // instruction formatted as `line`. A missing byte means one side ended.
+    Mismatch {
+        offset: usize,
+        expected: Option<u8>,
+        found: Option<u8>,
+        line: String,
+    },
+    // The instruction at `offset` decoded but its line doesn't assemble.
+    Assemble {
+        offset: usize,
+        error: AssembleError,
+        line: String,
+    },
+}
+
+impl VerifyError {
+    pub fn offset(&self) -> usize {
+        match self {
+            VerifyError::Mismatch { offset, .. } | VerifyError::Assemble { offset, .. } => *offset,
+        }
+    }
+}
+
+fn which_byte(byte: &Option<u8>) -> String {
+    match byte {
+        Some(byte) => format!("{:#04x}", byte),
+        None => String::from("nothing"),
+    }
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerifyError::Mismatch {
+                offset,
+                expected,
+                found,
+                line,
+            } => write!(
+                f,
+                "byte mismatch at offset {} in `{}`: expected {}, found {}",
+                offset,
+                line,
+                which_byte(expected),
+                which_byte(found)
+            ),
+            VerifyError::Assemble {
+                offset,
+                error,
+                line,
+            } => write!(
+                f,
+                "cannot reassemble `{}` at offset {}: {}",
+                line, offset, error
+            ),
+        }
+    }
+}
+
+impl Error for VerifyError {}
+
+fn compare_instruction(
+    contents: &[u8],
+    decoded: &DecodedInstruction,
+    line: &str,
+    rebuilt: &[u8],
+) -> Result<(), VerifyError> {
+    let original = &contents[decoded.offset..][..decoded.length];
+
+    // The first index where the two differ, counting running out of bytes.
+    let difference = (0..original.len().max(rebuilt.len()))
+        .find(|index| original.get(*index) != rebuilt.get(*index));
+
+    match difference {
+        Some(index) => Err(VerifyError::Mismatch {
+            offset: decoded.offset + index,
+            expected: original.get(index).copied(),
+            found: rebuilt.get(index).copied(),
+            line: line.to_string(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Decodes `contents`, formats every instruction as a listing line,
+/// assembles that line again and checks that the result is byte for byte
+/// the input. Undecodable bytes come back as the `db` lines the listing
+/// writes for them.
+pub fn verify(contents: &[u8]) -> Result<(), VerifyError> {
+    for decoded in Decoder::new(contents) {
+        // A `db` line rebuilds exactly the byte it was written for.
+        let Ok(decoded) = decoded else {
+            continue;
+        };
+
+        // Relative operands are written from `$`, so each line assembles on
+        // its own.
+        let line = format_instruction(&decoded);
+        let rebuilt = assemble(&line).map_err(|error| VerifyError::Assemble {
+            offset: decoded.offset,
+            error,
+            line: line.clone(),
+        })?;
+
+        // Every earlier instruction matched, so the first difference here
+        // is the first difference in the whole rebuilt buffer.
+        compare_instruction(contents, &decoded, &line, &rebuilt)?;
+    }
+
+    Ok(())
+}